@@ -1,8 +1,23 @@
-use crate::config::Config;
+use crate::{
+    config::Config,
+    moodle::{cache::ResponseCache, validation::Validations},
+    oidc,
+    rate_limit::RateLimiter,
+    secret_store,
+    user_slots::UserSlots,
+    vault,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub http_client: reqwest::Client,
     pub pool: sqlx::SqlitePool,
     pub config: &'static Config,
+    pub oidc: oidc::Verifier,
+    pub vault_tokens: vault::TokenCache,
+    pub secret_store: secret_store::Backend,
+    pub archive_slots: UserSlots,
+    pub message_limiter: RateLimiter,
+    pub moodle_cache: ResponseCache,
+    pub moodle_validations: Validations,
 }
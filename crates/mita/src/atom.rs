@@ -0,0 +1,140 @@
+//! Minimal [RFC 4287](https://www.rfc-editor.org/rfc/rfc4287) writer, just
+//! enough for subscription feeds.
+
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+pub struct Feed {
+    /// Must stay the same across renders.
+    pub id: String,
+    pub title: String,
+    /// Unix timestamp.
+    pub updated: i64,
+    pub entries: Vec<Entry>,
+}
+
+pub struct Entry {
+    /// Must stay the same across renders so feed readers don't show entries
+    /// again.
+    pub id: String,
+    pub title: String,
+    pub author: Option<String>,
+    pub category: Option<String>,
+    pub link: Option<String>,
+    /// Unix timestamp.
+    pub published: i64,
+    /// Unix timestamp.
+    pub updated: i64,
+    /// HTML, which must already be safe to show.
+    pub content: String,
+}
+
+pub fn render(feed: &Feed) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    element(&mut out, 1, "id", &feed.id);
+    element(&mut out, 1, "title", &feed.title);
+    element(&mut out, 1, "updated", &timestamp(feed.updated));
+    out.push_str("  <generator>mita</generator>\n");
+
+    for entry in &feed.entries {
+        out.push_str("  <entry>\n");
+        element(&mut out, 2, "id", &entry.id);
+        element(&mut out, 2, "title", &entry.title);
+        element(&mut out, 2, "published", &timestamp(entry.published));
+        element(&mut out, 2, "updated", &timestamp(entry.updated));
+        // atom requires an author, either on the feed or on every entry
+        out.push_str("    <author>\n");
+        element(
+            &mut out,
+            3,
+            "name",
+            entry.author.as_deref().unwrap_or("Unknown"),
+        );
+        out.push_str("    </author>\n");
+        if let Some(category) = &entry.category {
+            out.push_str(&format!("    <category term=\"{}\"/>\n", escape(category)));
+        }
+        if let Some(link) = &entry.link {
+            out.push_str(&format!(
+                "    <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n",
+                escape(link)
+            ));
+        }
+        out.push_str(&format!(
+            "    <content type=\"html\">{}</content>\n",
+            escape(&entry.content)
+        ));
+        out.push_str("  </entry>\n");
+    }
+
+    out.push_str("</feed>\n");
+    out
+}
+
+fn element(out: &mut String, depth: usize, name: &str, text: &str) {
+    out.push_str(&format!(
+        "{}<{name}>{}</{name}>\n",
+        "  ".repeat(depth),
+        escape(text)
+    ));
+}
+
+fn timestamp(unix: i64) -> String {
+    OffsetDateTime::from_unix_timestamp(unix)
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+        .format(&Rfc3339)
+        .expect("formatting a valid date never fails")
+}
+
+fn escape(text: &str) -> String {
+    text.chars()
+        // characters xml doesn't allow, even escaped
+        .filter(|&c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .fold(String::with_capacity(text.len()), |mut out, c| {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '"' => out.push_str("&quot;"),
+                _ => out.push(c),
+            }
+            out
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, render, Entry, Feed};
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(
+            escape("<b>a & \"b\"</b>\u{1}"),
+            "&lt;b&gt;a &amp; &quot;b&quot;&lt;/b&gt;"
+        );
+    }
+
+    #[test]
+    fn renders_entry() {
+        let atom = render(&Feed {
+            id: "tag:moodle.test,2023:announcements/1".into(),
+            title: "Announcements".into(),
+            updated: 1678608000,
+            entries: vec![Entry {
+                id: "tag:moodle.test,2023:discussion/4".into(),
+                title: "[CO3001] Room change".into(),
+                author: None,
+                category: Some("CO3001".into()),
+                link: Some("https://moodle.test/mod/forum/discuss.php?d=4&x=1".into()),
+                published: 1678608000,
+                updated: 1678608000,
+                content: "<p>H6</p>".into(),
+            }],
+        });
+
+        assert!(atom.contains("<updated>2023-03-12T08:00:00Z</updated>"));
+        assert!(atom.contains("<name>Unknown</name>"));
+        assert!(atom.contains("href=\"https://moodle.test/mod/forum/discuss.php?d=4&amp;x=1\""));
+        assert!(atom.contains("<content type=\"html\">&lt;p&gt;H6&lt;/p&gt;</content>"));
+    }
+}
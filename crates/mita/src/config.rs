@@ -1,18 +1,25 @@
+use std::collections::HashMap;
+
 use eyre::Context;
 use figment::{
     providers::{Env, Format, Serialized, Toml},
     Figment,
 };
 use reqwest::Url;
-use serde::{Deserialize, Serialize};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize, Serializer};
 
 #[derive(Deserialize, Serialize)]
 pub struct Config {
     pub app: AppConfig,
     pub database: DatabaseConfig,
     pub vault: VaultConfig,
-    pub oauth2: Option<OAuth2Config>,
+    pub oauth2: OAuth2Config,
     pub moodle: MoodleConfig,
+    pub secret_store: SecretStoreConfig,
+    pub grade_watch: GradeWatchConfig,
+    pub archive: ArchiveConfig,
+    pub messages: MessagesConfig,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -41,6 +48,87 @@ pub struct OAuth2Config {
 #[derive(Deserialize, Serialize)]
 pub struct MoodleConfig {
     pub url: Url,
+    /// Seconds before a token moodle accepted is validated again.
+    pub validation_interval: u64,
+    #[serde(default)]
+    pub cache: MoodleCacheConfig,
+}
+
+/// Caching of moodle responses, see [`crate::moodle::cache::ResponseCache`].
+#[derive(Default, Deserialize, Serialize)]
+pub struct MoodleCacheConfig {
+    /// Responses cached at once, across users.
+    #[serde(default)]
+    pub max_entries: usize,
+    /// Total size of the responses cached at once, across users.
+    #[serde(default)]
+    pub max_bytes: usize,
+    /// Seconds to cache each wsfunction's responses for. Functions not
+    /// listed aren't cached.
+    #[serde(default)]
+    pub ttls: HashMap<String, u64>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct GradeWatchConfig {
+    /// Seconds between grade snapshots.
+    pub interval: u64,
+}
+
+/// Limits of course archives.
+#[derive(Deserialize, Serialize)]
+pub struct ArchiveConfig {
+    /// Files downloaded at once for one archive.
+    pub concurrency: usize,
+    /// Archives built at once for one user.
+    pub max_archives: usize,
+    /// Largest archive, in bytes. Must stay under 4 GiB, archives aren't
+    /// ZIP64.
+    pub max_bytes: u64,
+}
+
+/// Limits of messages sent through mita.
+#[derive(Deserialize, Serialize)]
+pub struct MessagesConfig {
+    /// Longest message, in characters.
+    pub max_length: usize,
+    /// Messages one user may send within `rate_window`.
+    pub rate_limit: usize,
+    /// Seconds.
+    pub rate_window: u64,
+}
+
+/// Where moodle tokens are stored.
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum SecretStoreConfig {
+    /// KV secrets engine of the vault in [`VaultConfig`].
+    Vault,
+    /// `users` table of mita's own database, encrypted under `key`
+    /// (64 hex characters).
+    Sqlite {
+        #[serde(serialize_with = "expose")]
+        key: Secret<String>,
+    },
+    /// `users` table of mita's own database, encrypted by vault's transit
+    /// engine under `key_name`, which must be a derived key. `token` is mita's
+    /// own vault token, allowed to encrypt, decrypt and rewrap with the key.
+    Transit {
+        key_name: String,
+        #[serde(serialize_with = "expose")]
+        token: Secret<String>,
+        /// Seconds between checks for tokens encrypted with an old key version.
+        #[serde(default = "default_rewrap_interval")]
+        rewrap_interval: u64,
+    },
+}
+
+fn default_rewrap_interval() -> u64 {
+    60 * 60
+}
+
+fn expose<S: Serializer>(secret: &Secret<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(secret.expose_secret())
 }
 
 impl Config {
@@ -52,22 +140,33 @@ impl Config {
 
     pub fn dev() -> eyre::Result<Self> {
         Config::figment()
-            .extract()
-            .wrap_err("error reading dev config")
+            .extract::<Self>()
+            .wrap_err("error reading dev config")?
+            .validate()
     }
 
     pub fn production() -> eyre::Result<Self> {
         Config::figment()
             .select("production")
-            .extract()
-            .wrap_err("error reading prod config")
+            .extract::<Self>()
+            .wrap_err("error reading prod config")?
+            .validate()
     }
 
     pub fn test() -> eyre::Result<Self> {
         Figment::from(Serialized::defaults(Config::dev()?))
             .merge(Config::figment().select("test"))
-            .extract()
-            .wrap_err("error reading test config")
+            .extract::<Self>()
+            .wrap_err("error reading test config")?
+            .validate()
+    }
+
+    /// Rejects values that can't work, rather than failing once in use.
+    fn validate(self) -> eyre::Result<Self> {
+        if self.archive.max_bytes >= 1 << 32 {
+            eyre::bail!("archive.max_bytes must be under 4 GiB, archives aren't ZIP64");
+        }
+        Ok(self)
     }
 
     pub fn leak(self) -> &'static Self {
@@ -4,7 +4,13 @@ use eyre::WrapErr;
 use futures::future::BoxFuture;
 use sqlx::sqlite::SqlitePoolOptions;
 
-use crate::{app_state::AppState, config::Config, routes::router::app_router};
+use crate::{
+    app_state::AppState,
+    config::{Config, SecretStoreConfig},
+    grade_watch, oidc,
+    routes::router::app_router,
+    secret_store,
+};
 
 pub struct Server {
     addr: SocketAddr,
@@ -23,13 +29,54 @@ impl Server {
 
         sqlx::migrate!("../../db/migrations").run(&pool).await?;
 
-        let http_client = reqwest::Client::builder().build().unwrap();
+        // no overall timeout, file downloads and archives stream for long;
+        // calls that should be short set their own
+        let http_client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .build()
+            .unwrap();
 
-        let app = app_router(AppState {
+        let secret_store = secret_store::Backend::new(&http_client, config, &pool)?;
+
+        if let (
+            secret_store::Backend::Transit(database),
+            SecretStoreConfig::Transit {
+                rewrap_interval, ..
+            },
+        ) = (&secret_store, &config.secret_store)
+        {
+            tokio::spawn(
+                database
+                    .clone()
+                    .rewrap_periodically(Duration::from_secs(*rewrap_interval)),
+            );
+        }
+
+        let state = AppState {
+            secret_store,
+            oidc: oidc::Verifier::new(&http_client, &config.oauth2),
+            vault_tokens: Default::default(),
+            archive_slots: Default::default(),
+            message_limiter: Default::default(),
+            moodle_cache: Default::default(),
+            moodle_validations: Default::default(),
             http_client,
             pool,
             config,
-        });
+        };
+
+        if state.secret_store.readable_by_mita() {
+            tokio::spawn(grade_watch::watch(
+                state.clone(),
+                Duration::from_secs(config.grade_watch.interval),
+            ));
+        } else {
+            tracing::warn!(
+                "grade watching and notification channels need the sqlite or transit secret store"
+            );
+        }
+
+        let app = app_router(state);
 
         let server = axum::Server::bind(&addr).serve(app.into_make_service());
 
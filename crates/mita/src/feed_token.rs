@@ -0,0 +1,250 @@
+use std::ops::Deref;
+
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    XChaCha20Poly1305, XNonce,
+};
+use eyre::WrapErr;
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use thiserror::Error;
+
+use crate::moodle::token::MoodleToken;
+
+/// An unguessable token for clients that cannot send a bearer token, like
+/// calendar apps and feed readers. It consists of 64 characters in `a..f` or `0..9`.
+///
+/// Only a hash of the token is stored. The token also derives the key that
+/// encrypts the owner's moodle token, so the database alone can't be used to
+/// recover moodle tokens.
+///
+/// # Example
+///
+/// ```
+/// use mita::feed_token::FeedToken;
+/// use secrecy::ExposeSecret;
+///
+/// let token = FeedToken::generate();
+/// let parsed = token.expose_secret().parse::<FeedToken>().unwrap();
+/// assert_eq!(parsed.expose_secret(), token.expose_secret());
+/// ```
+#[derive(Clone, Debug)]
+pub struct FeedToken(Secret<String>);
+
+impl Deref for FeedToken {
+    type Target = Secret<String>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl std::str::FromStr for FeedToken {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = hex::decode(s).wrap_err("feed token is not a hex string")?;
+        <[u8; 32]>::try_from(bytes)
+            .map_err(|_| eyre::eyre!("feed token is not at correct length"))?;
+        Ok(Self(Secret::new(s.to_lowercase())))
+    }
+}
+
+impl FeedToken {
+    pub fn generate() -> Self {
+        let mut bytes = [0; 32];
+        OsRng.fill_bytes(&mut bytes);
+        Self(Secret::new(hex::encode(bytes)))
+    }
+
+    fn derive(&self, context: &[u8]) -> [u8; 32] {
+        Sha256::new()
+            .chain_update(context)
+            .chain_update(self.0.expose_secret())
+            .finalize()
+            .into()
+    }
+
+    fn lookup_hash(&self) -> Vec<u8> {
+        self.derive(b"mita feed token lookup").to_vec()
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(&self.derive(b"mita feed token key").into())
+    }
+
+    fn seal(&self, moodle_token: &MoodleToken) -> eyre::Result<Vec<u8>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, moodle_token.expose_secret().as_bytes())
+            .map_err(|_| eyre::eyre!("error encrypting moodle token"))?;
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    fn open(&self, sealed: &[u8]) -> eyre::Result<MoodleToken> {
+        if sealed.len() < 24 {
+            eyre::bail!("sealed moodle token too short");
+        }
+        let (nonce, ciphertext) = sealed.split_at(24);
+        let plaintext = self
+            .cipher()
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| eyre::eyre!("error decrypting moodle token"))?;
+        String::from_utf8(plaintext)
+            .wrap_err("moodle token is not utf-8")?
+            .parse()
+            .wrap_err("malformed moodle token inside feed token")
+    }
+}
+
+/// What a feed token gives access to. A token only works for its own kind
+/// of feed, and each kind is revoked separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeedKind {
+    Calendar,
+    Announcements,
+}
+
+impl FeedKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            FeedKind::Calendar => "calendar",
+            FeedKind::Announcements => "announcements",
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum FeedTokenError {
+    #[error("feed token not found")]
+    NotFound,
+    #[error("database error")]
+    Database(#[from] sqlx::Error),
+    #[error("unexpected error")]
+    Unexpected(#[from] eyre::Error),
+}
+
+impl FeedTokenError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            FeedTokenError::NotFound => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Creates a `kind` feed token for `owner`, replacing (and so revoking) the
+/// previous one of that kind if any.
+#[tracing::instrument(skip(pool, moodle_token))]
+pub async fn create(
+    pool: &SqlitePool,
+    owner: &str,
+    kind: FeedKind,
+    moodle_token: &MoodleToken,
+    now: i64,
+) -> Result<FeedToken, FeedTokenError> {
+    let feed_token = FeedToken::generate();
+
+    sqlx::query(
+        "INSERT INTO feed_tokens (owner, kind, token_hash, moodle_token, created_at)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT (owner, kind) DO UPDATE SET
+            token_hash = excluded.token_hash,
+            moodle_token = excluded.moodle_token,
+            created_at = excluded.created_at",
+    )
+    .bind(owner)
+    .bind(kind.as_str())
+    .bind(feed_token.lookup_hash())
+    .bind(feed_token.seal(moodle_token)?)
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    Ok(feed_token)
+}
+
+/// Returns whether there was a feed token to revoke.
+#[tracing::instrument(skip(pool))]
+pub async fn revoke(
+    pool: &SqlitePool,
+    owner: &str,
+    kind: FeedKind,
+) -> Result<bool, FeedTokenError> {
+    let res = sqlx::query("DELETE FROM feed_tokens WHERE owner = ? AND kind = ?")
+        .bind(owner)
+        .bind(kind.as_str())
+        .execute(pool)
+        .await?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+/// Revokes every feed token of `owner`.
+#[tracing::instrument(skip(pool))]
+pub async fn revoke_all(pool: &SqlitePool, owner: &str) -> Result<(), FeedTokenError> {
+    sqlx::query("DELETE FROM feed_tokens WHERE owner = ?")
+        .bind(owner)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Finds the moodle token of the user owning `feed_token`, if it is a
+/// `kind` feed token.
+#[tracing::instrument(skip(pool, feed_token))]
+pub async fn resolve(
+    pool: &SqlitePool,
+    kind: FeedKind,
+    feed_token: &FeedToken,
+) -> Result<MoodleToken, FeedTokenError> {
+    let sealed: Option<(Vec<u8>,)> =
+        sqlx::query_as("SELECT moodle_token FROM feed_tokens WHERE token_hash = ? AND kind = ?")
+            .bind(feed_token.lookup_hash())
+            .bind(kind.as_str())
+            .fetch_optional(pool)
+            .await?;
+
+    let (sealed,) = sealed.ok_or(FeedTokenError::NotFound)?;
+
+    Ok(feed_token.open(&sealed)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+    use secrecy::ExposeSecret;
+
+    use super::FeedToken;
+    use crate::moodle::token::MoodleToken;
+
+    #[test]
+    fn seal_and_open() -> eyre::Result<()> {
+        let feed_token = FeedToken::generate();
+        let moodle_token = "ab".repeat(16).parse::<MoodleToken>()?;
+
+        let sealed = feed_token.seal(&moodle_token)?;
+        let opened = assert_ok!(feed_token.open(&sealed));
+
+        assert_eq!(opened.expose_secret(), moodle_token.expose_secret());
+        Ok(())
+    }
+
+    #[test]
+    fn other_token_cannot_open() -> eyre::Result<()> {
+        let moodle_token = "ab".repeat(16).parse::<MoodleToken>()?;
+        let sealed = FeedToken::generate().seal(&moodle_token)?;
+
+        let _ = assert_err!(FeedToken::generate().open(&sealed));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_wrong_length() {
+        let _ = assert_err!("ab".repeat(16).parse::<FeedToken>());
+    }
+}
@@ -0,0 +1,446 @@
+//! Periodically snapshots the grades of every registered user and records
+//! what changed since the previous snapshot.
+//!
+//! Only secret stores kept in mita's own database can be read without the
+//! user's id token, so nothing is watched with the vault backend.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqlitePool};
+
+use crate::{
+    app_state::AppState,
+    clock::now,
+    moodle,
+    notify::{self, Notification},
+    routes::grades::get::{course_grade_items, GradeItemResponse},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// The item had no grade before.
+    Released,
+    Changed,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct GradeChange {
+    /// Orders changes detected at the same time, see [`changes_since`].
+    pub id: i64,
+    pub course_id: i64,
+    pub course_name: String,
+    pub item_id: i64,
+    pub item_name: String,
+    pub kind: ChangeKind,
+    pub old_grade: Option<f64>,
+    pub new_grade: Option<f64>,
+    pub old_grade_formatted: Option<String>,
+    pub new_grade_formatted: Option<String>,
+    /// Unix timestamp.
+    pub detected_at: i64,
+}
+
+/// Longest time spent on the grades of one user, notifications included.
+const USER_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Runs [`watch_once`] every `interval` until the server stops.
+pub async fn watch(state: AppState, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        if let Err(error) = watch_once(&state).await {
+            tracing::error!(?error, "error watching grades");
+        }
+    }
+}
+
+/// Snapshots the grades of every user whose moodle token mita can read.
+#[tracing::instrument(skip(state))]
+pub async fn watch_once(state: &AppState) -> Result<(), sqlx::Error> {
+    let subjects = state.secret_store.stored_subjects().await?;
+
+    for subject in subjects {
+        // one hung user mustn't hold up the others
+        match tokio::time::timeout(USER_TIMEOUT, watch_user(state, &subject)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => tracing::warn!(%subject, ?error, "error watching grades of user"),
+            Err(_) => tracing::warn!(%subject, "timed out watching grades of user"),
+        }
+    }
+
+    Ok(())
+}
+
+async fn watch_user(state: &AppState, subject: &str) -> eyre::Result<()> {
+    let Some(secret_store) = state.secret_store.open_stored(subject) else {
+        return Ok(());
+    };
+    let moodle_token = secret_store.get_moodle_token().await?;
+    let moodle = moodle::Client::new(
+        &state.http_client,
+        &state.config.moodle,
+        moodle_token,
+        state.moodle_cache.refreshing(),
+        state.moodle_validations.clone(),
+        Some(secret_store.clone()),
+    )
+    .await?;
+
+    let courses = moodle.get_courses().await?;
+    forget_courses_except(
+        &state.pool,
+        subject,
+        &courses.iter().map(|c| c.id).collect::<Vec<_>>(),
+    )
+    .await?;
+
+    let mut changes = vec![];
+    for course in courses {
+        let items = course_grade_items(&moodle, course.id).await?;
+        changes.extend(
+            record(
+                &state.pool,
+                subject,
+                course.id,
+                &course.fullname,
+                &items,
+                now(),
+            )
+            .await?,
+        );
+    }
+
+    if !changes.is_empty() {
+        notify::send(
+            &state.pool,
+            subject,
+            &Notification::GradeChanges { changes },
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Replaces the snapshot of a course's grade items, returning the changes
+/// against the previous one. The first snapshot of a course is only a
+/// baseline and yields no changes, even if the course had no items yet.
+/// Items gone from moodle are dropped from the snapshot.
+pub async fn record(
+    pool: &SqlitePool,
+    subject: &str,
+    course_id: u64,
+    course_name: &str,
+    items: &[GradeItemResponse],
+    now: i64,
+) -> Result<Vec<GradeChange>, sqlx::Error> {
+    let course_id = course_id as i64;
+    let mut tx = pool.begin().await?;
+
+    let previous: Vec<(i64, Option<f64>, Option<String>)> = sqlx::query_as(
+        "SELECT item_id, grade, grade_formatted FROM grade_snapshots
+        WHERE subject = ? AND course_id = ?",
+    )
+    .bind(subject)
+    .bind(course_id)
+    .fetch_all(&mut tx)
+    .await?;
+    // a course seen for the first time, whether or not it has items
+    let baseline = sqlx::query(
+        "INSERT INTO grade_watched_courses (subject, course_id) VALUES (?, ?)
+        ON CONFLICT DO NOTHING",
+    )
+    .bind(subject)
+    .bind(course_id)
+    .execute(&mut tx)
+    .await?
+    .rows_affected()
+        == 1;
+
+    sqlx::query("DELETE FROM grade_snapshots WHERE subject = ? AND course_id = ?")
+        .bind(subject)
+        .bind(course_id)
+        .execute(&mut tx)
+        .await?;
+
+    let mut changes = vec![];
+    for item in items {
+        let item_id = item.id as i64;
+        let (old_grade, old_grade_formatted) = previous
+            .iter()
+            .find(|(id, ..)| *id == item_id)
+            .map(|(_, grade, formatted)| (*grade, formatted.clone()))
+            .unwrap_or_default();
+
+        let kind = match (old_grade, item.grade) {
+            _ if baseline => None,
+            (None, Some(_)) => Some(ChangeKind::Released),
+            (Some(_), Some(_))
+                if old_grade != item.grade || old_grade_formatted != item.grade_formatted =>
+            {
+                Some(ChangeKind::Changed)
+            }
+            _ => None,
+        };
+
+        if let Some(kind) = kind {
+            changes.push(GradeChange {
+                id: 0,
+                course_id,
+                course_name: course_name.into(),
+                item_id,
+                item_name: item.name.clone(),
+                kind,
+                old_grade,
+                new_grade: item.grade,
+                old_grade_formatted,
+                new_grade_formatted: item.grade_formatted.clone(),
+                detected_at: now,
+            });
+        }
+
+        sqlx::query(
+            "INSERT INTO grade_snapshots (subject, course_id, item_id, grade, grade_formatted)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (subject, item_id) DO UPDATE SET
+                course_id = excluded.course_id,
+                grade = excluded.grade,
+                grade_formatted = excluded.grade_formatted",
+        )
+        .bind(subject)
+        .bind(course_id)
+        .bind(item_id)
+        .bind(item.grade)
+        .bind(&item.grade_formatted)
+        .execute(&mut tx)
+        .await?;
+    }
+
+    for change in &mut changes {
+        change.id = sqlx::query(
+            "INSERT INTO grade_changes (subject, course_id, course_name, item_id, item_name,
+                kind, old_grade, new_grade, old_grade_formatted, new_grade_formatted, detected_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(subject)
+        .bind(change.course_id)
+        .bind(&change.course_name)
+        .bind(change.item_id)
+        .bind(&change.item_name)
+        .bind(&change.kind)
+        .bind(change.old_grade)
+        .bind(change.new_grade)
+        .bind(&change.old_grade_formatted)
+        .bind(&change.new_grade_formatted)
+        .bind(change.detected_at)
+        .execute(&mut tx)
+        .await?
+        .last_insert_rowid();
+    }
+
+    tx.commit().await?;
+
+    Ok(changes)
+}
+
+/// Changes after the one detected at `since` with id `after`, oldest first.
+/// Passing the last change's `detected_at` and `id` pages through changes
+/// however many were detected at once.
+pub async fn changes_since(
+    pool: &SqlitePool,
+    subject: &str,
+    since: i64,
+    after: i64,
+    limit: u32,
+) -> Result<Vec<GradeChange>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT id, course_id, course_name, item_id, item_name, kind, old_grade, new_grade,
+            old_grade_formatted, new_grade_formatted, detected_at
+        FROM grade_changes
+        WHERE subject = ? AND (detected_at > ? OR (detected_at = ? AND id > ?))
+        ORDER BY detected_at, id
+        LIMIT ?",
+    )
+    .bind(subject)
+    .bind(since)
+    .bind(since)
+    .bind(after)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Drops the snapshots of courses the user is no longer enrolled in.
+async fn forget_courses_except(
+    pool: &SqlitePool,
+    subject: &str,
+    course_ids: &[u64],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let watched: Vec<(i64,)> =
+        sqlx::query_as("SELECT course_id FROM grade_watched_courses WHERE subject = ?")
+            .bind(subject)
+            .fetch_all(&mut tx)
+            .await?;
+
+    for (course_id,) in watched {
+        if course_ids.contains(&(course_id as u64)) {
+            continue;
+        }
+        for table in ["grade_snapshots", "grade_watched_courses"] {
+            sqlx::query(&format!(
+                "DELETE FROM {table} WHERE subject = ? AND course_id = ?"
+            ))
+            .bind(subject)
+            .bind(course_id)
+            .execute(&mut tx)
+            .await?;
+        }
+    }
+
+    tx.commit().await
+}
+
+/// Drops the snapshots and changes of a user leaving the service.
+pub async fn forget(pool: &SqlitePool, subject: &str) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM grade_snapshots WHERE subject = ?")
+        .bind(subject)
+        .execute(&mut tx)
+        .await?;
+    sqlx::query("DELETE FROM grade_watched_courses WHERE subject = ?")
+        .bind(subject)
+        .execute(&mut tx)
+        .await?;
+    sqlx::query("DELETE FROM grade_changes WHERE subject = ?")
+        .bind(subject)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::SqlitePool;
+
+    use super::{changes_since, forget_courses_except, record, ChangeKind};
+    use crate::routes::grades::get::GradeItemResponse;
+
+    fn item(id: u64, grade: Option<f64>) -> GradeItemResponse {
+        GradeItemResponse {
+            id,
+            name: format!("Lab {id}"),
+            item_type: "mod".into(),
+            module: Some("assign".into()),
+            cmid: None,
+            grade,
+            grade_formatted: grade.map(|g| format!("{g:.2}")),
+            range: None,
+            percentage: None,
+            weight: None,
+            feedback: None,
+            graded_at: None,
+            hidden: false,
+        }
+    }
+
+    async fn pool() -> eyre::Result<SqlitePool> {
+        let pool = SqlitePool::connect("sqlite::memory:").await?;
+        sqlx::migrate!("../../db/migrations").run(&pool).await?;
+        Ok(pool)
+    }
+
+    #[tokio::test]
+    async fn first_snapshot_is_baseline() -> eyre::Result<()> {
+        let pool = pool().await?;
+
+        let changes = record(&pool, "khang", 3, "OS", &[item(1, Some(5.0))], 10).await?;
+
+        assert!(changes.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn detects_released_and_changed_grades() -> eyre::Result<()> {
+        let pool = pool().await?;
+        let before = [item(1, Some(5.0)), item(2, None), item(3, Some(7.0))];
+        record(&pool, "khang", 3, "OS", &before, 10).await?;
+
+        let after = [item(1, Some(6.0)), item(2, Some(9.0)), item(3, Some(7.0))];
+        let changes = record(&pool, "khang", 3, "OS", &after, 20).await?;
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].item_id, 1);
+        assert_eq!(changes[0].kind, ChangeKind::Changed);
+        assert_eq!(changes[0].old_grade, Some(5.0));
+        assert_eq!(changes[1].item_id, 2);
+        assert_eq!(changes[1].kind, ChangeKind::Released);
+
+        let stored = changes_since(&pool, "khang", 15, 0, 100).await?;
+        assert_eq!(stored.len(), 2);
+        assert!(changes_since(&pool, "minh", 0, 0, 100).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn course_without_items_is_baseline_once() -> eyre::Result<()> {
+        let pool = pool().await?;
+        record(&pool, "khang", 3, "OS", &[], 10).await?;
+
+        let changes = record(&pool, "khang", 3, "OS", &[item(1, Some(5.0))], 20).await?;
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ChangeKind::Released);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn forgets_removed_items_and_courses() -> eyre::Result<()> {
+        let pool = pool().await?;
+        record(
+            &pool,
+            "khang",
+            3,
+            "OS",
+            &[item(1, Some(5.0)), item(2, None)],
+            10,
+        )
+        .await?;
+        record(&pool, "khang", 4, "DB", &[item(7, Some(5.0))], 10).await?;
+
+        record(&pool, "khang", 3, "OS", &[item(2, None)], 20).await?;
+        forget_courses_except(&pool, "khang", &[3]).await?;
+
+        let snapshots: Vec<(i64,)> =
+            sqlx::query_as("SELECT item_id FROM grade_snapshots WHERE subject = 'khang'")
+                .fetch_all(&pool)
+                .await?;
+        assert_eq!(snapshots, [(2,)]);
+        // back in the course, its first snapshot is a baseline again
+        let changes = record(&pool, "khang", 4, "DB", &[item(7, Some(6.0))], 30).await?;
+        assert!(changes.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pages_past_changes_detected_at_once() -> eyre::Result<()> {
+        let pool = pool().await?;
+        let before: Vec<_> = (1..=5).map(|id| item(id, None)).collect();
+        record(&pool, "khang", 3, "OS", &before, 10).await?;
+        let after: Vec<_> = (1..=5).map(|id| item(id, Some(5.0))).collect();
+        record(&pool, "khang", 3, "OS", &after, 20).await?;
+
+        let first = changes_since(&pool, "khang", 0, 0, 3).await?;
+        let last = &first[2];
+        let rest = changes_since(&pool, "khang", last.detected_at, last.id, 3).await?;
+
+        assert_eq!(first.len(), 3);
+        assert_eq!(rest.len(), 2);
+        assert!(rest.iter().all(|change| change.id > last.id));
+        Ok(())
+    }
+}
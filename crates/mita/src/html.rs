@@ -0,0 +1,295 @@
+//! Moodle returns user written text, such as grade feedback, as HTML.
+
+use ammonia::{Builder, UrlRelative};
+use once_cell::sync::Lazy;
+
+/// Converts an HTML fragment to plain text. Tags and comments are dropped,
+/// as is the content of [`DROPPED_TAGS`], block elements and `<br>` become
+/// line breaks and entities are decoded.
+pub fn to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find(['<', '&']) {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+        } else if rest.starts_with('<') && !starts_tag(rest) {
+            text.push('<');
+            rest = &rest[1..];
+        } else if rest.starts_with('<') {
+            let end = tag_end(rest);
+            let inner = &rest[1..end];
+            rest = &rest[(end + 1).min(rest.len())..];
+            let name = tag_name(inner);
+            if !inner.starts_with('/') && DROPPED_TAGS.contains(&name.as_str()) {
+                rest = skip_element(rest, &name);
+            } else if is_line_break(&name) {
+                text.push('\n');
+            }
+        } else {
+            match rest
+                .find(';')
+                .and_then(|end| Some((decode_entity(&rest[1..end])?, end)))
+            {
+                Some((c, end)) => {
+                    text.push(c);
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    text.push('&');
+                    rest = &rest[1..];
+                }
+            }
+        }
+    }
+    text.push_str(rest);
+
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Whether the `<` at the start of `html` opens a tag, rather than being
+/// text such as `a < b`.
+fn starts_tag(html: &str) -> bool {
+    html[1..]
+        .chars()
+        .next()
+        .is_some_and(|c| c == '/' || c == '!' || c.is_ascii_alphabetic())
+}
+
+/// Lowercase name of the tag whose inside, between `<` and `>`, is `tag`.
+fn tag_name(tag: &str) -> String {
+    tag.trim_start_matches('/')
+        .split(|c: char| c.is_whitespace() || c == '/')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+fn is_line_break(name: &str) -> bool {
+    matches!(
+        name,
+        "br" | "p" | "div" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+    )
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = match entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+            {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => entity.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+/// Tags kept by [`sanitize`], with the attributes each may keep.
+const ALLOWED_TAGS: &[(&str, &[&str])] = &[
+    ("a", &["href", "title"]),
+    ("b", &[]),
+    ("blockquote", &[]),
+    ("br", &[]),
+    ("code", &[]),
+    ("div", &[]),
+    ("em", &[]),
+    ("h1", &[]),
+    ("h2", &[]),
+    ("h3", &[]),
+    ("h4", &[]),
+    ("h5", &[]),
+    ("h6", &[]),
+    ("hr", &[]),
+    ("i", &[]),
+    ("img", &["src", "alt", "width", "height"]),
+    ("li", &[]),
+    ("ol", &[]),
+    ("p", &[]),
+    ("pre", &[]),
+    ("span", &[]),
+    ("strong", &[]),
+    ("sub", &[]),
+    ("sup", &[]),
+    ("table", &[]),
+    ("tbody", &[]),
+    ("td", &["colspan", "rowspan"]),
+    ("th", &["colspan", "rowspan"]),
+    ("thead", &[]),
+    ("tr", &[]),
+    ("u", &[]),
+    ("ul", &[]),
+];
+
+/// Tags dropped along with their content.
+const DROPPED_TAGS: &[&str] = &[
+    "script", "style", "iframe", "object", "embed", "noscript", "template", "svg", "math",
+    "textarea", "select",
+];
+
+/// Schemes links may use, images only the first two.
+const URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+static SANITIZER: Lazy<Builder<'static>> = Lazy::new(|| {
+    let mut builder = Builder::empty();
+    builder
+        .tags(ALLOWED_TAGS.iter().map(|(tag, _)| *tag).collect())
+        .tag_attributes(
+            ALLOWED_TAGS
+                .iter()
+                .map(|(tag, attributes)| (*tag, attributes.iter().copied().collect()))
+                .collect(),
+        )
+        .clean_content_tags(DROPPED_TAGS.iter().copied().collect())
+        .url_schemes(URL_SCHEMES.iter().copied().collect())
+        .url_relative(UrlRelative::Deny)
+        .link_rel(Some("noopener noreferrer"))
+        .attribute_filter(|tag, attribute, value| match (tag, attribute) {
+            ("img", "src")
+                if value
+                    .trim_start()
+                    .to_ascii_lowercase()
+                    .starts_with("mailto:") =>
+            {
+                None
+            }
+            _ => Some(value.into()),
+        });
+    builder
+});
+
+/// Keeps the formatting of an HTML fragment while removing anything that
+/// could run or load active content, so that it can be shown by other apps,
+/// such as feed readers. Only known tags and attributes are kept, links must
+/// be `http`, `https` or `mailto` and images `http` or `https`. Unclosed tags
+/// are closed.
+pub fn sanitize(html: &str) -> String {
+    SANITIZER.clean(html).to_string()
+}
+
+/// Index of the `>` ending the tag at the start of `html`, ignoring those in
+/// quoted attribute values, or the length of `html` if it doesn't end.
+fn tag_end(html: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in html.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '>') => return i,
+            _ => {}
+        }
+    }
+    html.len()
+}
+
+/// Skips past the closing tag of `name`, or everything if it isn't closed.
+fn skip_element<'a>(html: &'a str, name: &str) -> &'a str {
+    let lowercase = html.to_ascii_lowercase();
+    match lowercase.find(&format!("</{name}")) {
+        Some(start) => {
+            let end = lowercase[start..]
+                .find('>')
+                .map_or(html.len(), |i| start + i + 1);
+            &html[end..]
+        }
+        None => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sanitize, to_text};
+
+    #[test]
+    fn strips_tags() {
+        assert_eq!(
+            to_text("<p>Good <strong>job</strong>!</p><p>See me<br/>after class</p>"),
+            "Good job!\nSee me\nafter class"
+        );
+    }
+
+    #[test]
+    fn drops_scripts_styles_and_comments() {
+        assert_eq!(
+            to_text("<style>p { color: red }</style><p>Hi<!-- note --></p><SCRIPT>alert(1)</SCRIPT>there"),
+            "Hi\nthere"
+        );
+    }
+
+    #[test]
+    fn decodes_entities() {
+        assert_eq!(
+            to_text("a &lt; b &amp;&amp; c&nbsp;&gt; d &#273;&#x1EA1;t"),
+            "a < b && c > d đạt"
+        );
+    }
+
+    #[test]
+    fn keeps_stray_less_than() {
+        assert_eq!(to_text("score < 5 and > 3"), "score < 5 and > 3");
+        assert_eq!(to_text("<p>1 <2</p> <"), "1 <2\n<");
+    }
+
+    #[test]
+    fn keeps_stray_ampersands() {
+        assert_eq!(to_text("Q&A; R&D"), "Q&A; R&D");
+    }
+
+    #[test]
+    fn sanitize_keeps_formatting() {
+        assert_eq!(
+            sanitize(
+                r#"<p class="x">Room <b>H6</b><br/>see <a href="https://e.test/a?b=1&amp;c=2" onclick="x()">map</a></p>"#
+            ),
+            r#"<p>Room <b>H6</b><br>see <a href="https://e.test/a?b=1&amp;c=2" rel="noopener noreferrer">map</a></p>"#
+        );
+    }
+
+    #[test]
+    fn sanitize_closes_tags() {
+        assert_eq!(
+            sanitize("<table><tr><td><a href=\"https://e.test\">unclosed"),
+            r#"<table><tbody><tr><td><a href="https://e.test" rel="noopener noreferrer">unclosed</a></td></tr></tbody></table>"#
+        );
+    }
+
+    #[test]
+    fn sanitize_removes_active_content() {
+        assert_eq!(
+            sanitize(
+                r#"a<script>alert("<p>")</script>b<style>p{}</style><!-- x -->c<iframe src="x"></iframe>"#
+            ),
+            "abc"
+        );
+        assert_eq!(
+            sanitize(
+                r#"<a href=" java	script:alert(1)">x</a><img src="data:image/png" onerror="y"><IMG SRC='https://e.test/a.png'>"#
+            ),
+            r#"<a rel="noopener noreferrer">x</a><img><img src="https://e.test/a.png">"#
+        );
+        assert_eq!(
+            sanitize(r#"<img src="mailto:a@e.test"><a href="/relative">x</a>"#),
+            r#"<img><a rel="noopener noreferrer">x</a>"#
+        );
+        assert_eq!(
+            sanitize(r#"<a title='"><script>'>x</a>"#),
+            r#"<a title="&quot;><script>" rel="noopener noreferrer">x</a>"#
+        );
+        assert_eq!(sanitize("<unknown>text</unknown> 1 > 0"), "text 1 &gt; 0");
+    }
+}
@@ -0,0 +1,132 @@
+//! Minimal [RFC 5545](https://www.rfc-editor.org/rfc/rfc5545) writer, just
+//! enough for subscription feeds.
+
+use std::fmt::Write;
+
+use time::{macros::format_description, OffsetDateTime};
+
+pub struct Event {
+    /// Must stay the same across renders so calendar apps update events
+    /// instead of duplicating them.
+    pub uid: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    /// Unix timestamp.
+    pub start: i64,
+    /// Minutes before `start` to remind the user.
+    pub alarms: Vec<u32>,
+}
+
+pub fn render(name: &str, events: &[Event], now: i64) -> String {
+    let mut out = String::new();
+    line(&mut out, "BEGIN:VCALENDAR");
+    line(&mut out, "VERSION:2.0");
+    line(&mut out, "PRODID:-//mita//mita//EN");
+    line(&mut out, "CALSCALE:GREGORIAN");
+    line(&mut out, "METHOD:PUBLISH");
+    line(&mut out, &format!("X-WR-CALNAME:{}", escape(name)));
+
+    for event in events {
+        line(&mut out, "BEGIN:VEVENT");
+        line(&mut out, &format!("UID:{}", escape(&event.uid)));
+        line(&mut out, &format!("DTSTAMP:{}", timestamp(now)));
+        line(&mut out, &format!("DTSTART:{}", timestamp(event.start)));
+        line(&mut out, &format!("DTEND:{}", timestamp(event.start)));
+        line(&mut out, &format!("SUMMARY:{}", escape(&event.summary)));
+        if let Some(description) = &event.description {
+            line(&mut out, &format!("DESCRIPTION:{}", escape(description)));
+        }
+        if let Some(url) = &event.url {
+            line(&mut out, &format!("URL:{url}"));
+        }
+        for minutes in &event.alarms {
+            line(&mut out, "BEGIN:VALARM");
+            line(&mut out, "ACTION:DISPLAY");
+            line(&mut out, &format!("DESCRIPTION:{}", escape(&event.summary)));
+            line(&mut out, &format!("TRIGGER:-PT{minutes}M"));
+            line(&mut out, "END:VALARM");
+        }
+        line(&mut out, "END:VEVENT");
+    }
+
+    line(&mut out, "END:VCALENDAR");
+    out
+}
+
+fn timestamp(unix: i64) -> String {
+    OffsetDateTime::from_unix_timestamp(unix)
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+        .format(format_description!(
+            "[year][month][day]T[hour][minute][second]Z"
+        ))
+        .expect("formatting a valid date never fails")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Writes a content line, folded at 75 octets without splitting characters.
+fn line(out: &mut String, content: &str) {
+    let mut len = 0;
+    for c in content.chars() {
+        if len + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            // the leading space counts toward the next line
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    write!(out, "\r\n").expect("writing to a string never fails");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, line, render, Event};
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(escape("a,b;c\\d\ne"), "a\\,b\\;c\\\\d\\ne");
+    }
+
+    #[test]
+    fn folds_long_lines_on_char_boundaries() {
+        let mut out = String::new();
+        line(&mut out, &"ệ".repeat(40));
+
+        for l in out.split("\r\n") {
+            assert!(l.len() <= 75);
+        }
+        assert_eq!(out.replace("\r\n ", ""), format!("{}\r\n", "ệ".repeat(40)));
+    }
+
+    #[test]
+    fn renders_event_with_alarm() {
+        let ics = render(
+            "Deadlines",
+            &[Event {
+                uid: "assign-1@mita".into(),
+                summary: "[CO3001] Lab 1".into(),
+                description: None,
+                url: Some("https://moodle.test/mod/assign/view.php?id=1".into()),
+                start: 1678608000,
+                alarms: vec![60],
+            }],
+            0,
+        );
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.contains("UID:assign-1@mita\r\n"));
+        assert!(ics.contains("DTSTART:20230312T080000Z\r\n"));
+        assert!(ics.contains("DTSTAMP:19700101T000000Z\r\n"));
+        assert!(ics.contains("BEGIN:VALARM\r\nACTION:DISPLAY\r\n"));
+        assert!(ics.contains("TRIGGER:-PT60M\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    }
+}
@@ -1,8 +1,21 @@
 pub mod app_state;
+pub mod atom;
+pub mod clock;
 pub mod config;
 pub mod entrypoint;
+pub mod feed_token;
+pub mod grade_watch;
+pub mod html;
+pub mod ics;
 pub mod middlewares;
 pub mod moodle;
+pub mod notify;
+pub mod oidc;
+pub mod rate_limit;
+pub mod registration;
 pub mod routes;
+pub mod secret_store;
 pub mod telemetry;
+pub mod user_slots;
 pub mod vault;
+pub mod zip;
@@ -1,6 +1,6 @@
 use axum::{
     extract::State,
-    http::Request,
+    http::{header::CACHE_CONTROL, HeaderMap, Request},
     middleware::Next,
     response::{IntoResponse, Response},
     Extension,
@@ -11,29 +11,51 @@ use thiserror::Error;
 use crate::{
     app_state::AppState,
     moodle::{self, error::MoodleError},
-    vault::{self, VaultError},
+    secret_store::{DynSecretStore, SecretStoreError},
 };
 
-#[tracing::instrument(skip(vault, state, req, next))]
+#[tracing::instrument(skip(secret_store, state, req, next))]
 pub async fn build_moodle_client<B>(
-    vault: Extension<vault::Client>,
+    secret_store: Extension<DynSecretStore>,
     state: State<AppState>,
     mut req: Request<B>,
     next: Next<B>,
 ) -> Result<Response, BuildMoodleError> {
-    let token = vault.get_moodle_token().await?;
+    let token = secret_store.get_moodle_token().await?;
 
-    let moodle = moodle::Client::new(&state.http_client, &state.config.moodle, token).await?;
+    let cache = match no_cache(req.headers()) {
+        true => state.moodle_cache.refreshing(),
+        false => state.moodle_cache.clone(),
+    };
+    let moodle = moodle::Client::new(
+        &state.http_client,
+        &state.config.moodle,
+        token,
+        cache,
+        state.moodle_validations.clone(),
+        Some(secret_store.0.clone()),
+    )
+    .await?;
 
     req.extensions_mut().insert(moodle);
 
     Ok(next.run(req).await)
 }
 
+/// Whether the client asked for fresh data with `Cache-Control: no-cache`.
+fn no_cache(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-cache"))
+}
+
 #[derive(Error, Debug)]
 pub enum BuildMoodleError {
-    #[error("error getting moodle token from vault")]
-    GetToken(#[from] VaultError),
+    #[error("error getting moodle token from secret store")]
+    GetToken(#[from] SecretStoreError),
     #[error("error building moodle client using token")]
     BuildClient(#[from] MoodleError),
 }
@@ -42,10 +64,11 @@ impl IntoResponse for BuildMoodleError {
     fn into_response(self) -> Response {
         let status = match &self {
             BuildMoodleError::GetToken(e) => e.status(),
+            BuildMoodleError::BuildClient(MoodleError::StaleToken) => StatusCode::GONE,
             BuildMoodleError::BuildClient(_) => StatusCode::INTERNAL_SERVER_ERROR,
         };
         let service = match &self {
-            BuildMoodleError::GetToken(_) => "vault",
+            BuildMoodleError::GetToken(_) => "secret_store",
             BuildMoodleError::BuildClient(_) => "moodle",
         };
         tracing::error!(%service, %status, error = ?self);
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use axum::{
     extract::State,
     http::Request,
@@ -9,6 +11,8 @@ use thiserror::Error;
 
 use crate::{
     app_state::AppState,
+    oidc::OidcError,
+    secret_store::{vault::VaultStore, DynSecretStore},
     vault::{self, VaultError},
 };
 
@@ -19,19 +23,49 @@ pub async fn authenticate<B>(
     mut req: Request<B>,
     next: Next<B>,
 ) -> Result<Response, AuthError> {
-    let vault = vault::Client::login(&state.http_client, &state.config.vault, &id_token.0).await?;
-    req.extensions_mut().insert(vault);
+    // reject bad tokens before they reach vault
+    let claims = state.oidc.verify(&id_token.0).await?;
+
+    let secret_store: DynSecretStore = match state.secret_store.open_stored(&claims.sub) {
+        Some(secret_store) => secret_store,
+        // vault only hands out the user's secrets to the user
+        None => Arc::new(VaultStore::new(
+            vault::Client::login(
+                &state.http_client,
+                &state.config.vault,
+                &state.vault_tokens,
+                &claims.sub,
+                &id_token.0,
+            )
+            .await?,
+            state.pool.clone(),
+            &claims.sub,
+        )),
+    };
+    req.extensions_mut().insert(claims);
+    req.extensions_mut().insert(secret_store);
     Ok(next.run(req).await)
 }
 
 #[derive(Error, Debug)]
-#[error(transparent)]
-pub struct AuthError(#[from] VaultError);
+pub enum AuthError {
+    #[error("error verifying id token")]
+    Verify(#[from] OidcError),
+    #[error("error logging into vault")]
+    Vault(#[from] VaultError),
+}
 
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
-        let status = self.0.status();
-        tracing::error!(service = "vault", %status, error = ?self, "error logging into vault");
+        let status = match &self {
+            AuthError::Verify(e) => e.status(),
+            AuthError::Vault(e) => e.status(),
+        };
+        let service = match &self {
+            AuthError::Verify(_) => "oidc",
+            AuthError::Vault(_) => "vault",
+        };
+        tracing::error!(%service, %status, error = ?self);
         status.into_response()
     }
 }
@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+
+use super::{error::MoodleWarning, function::WsFunction};
+
+pub struct GetAssignments;
+
+impl WsFunction for GetAssignments {
+    const NAME: &'static str = "mod_assign_get_assignments";
+    type Params = GetAssignmentsParams;
+    type Response = AssignmentCourses;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetAssignmentsParams {
+    pub courseids: Vec<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignmentCourses {
+    pub courses: Vec<AssignmentCourse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignmentCourse {
+    pub id: u64,
+    pub fullname: String,
+    pub assignments: Vec<Assignment>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Assignment {
+    pub id: u64,
+    pub cmid: u64,
+    pub course: u64,
+    pub name: String,
+    /// Unix timestamp, 0 when the assignment has no due date.
+    pub duedate: i64,
+    /// Unix timestamp, 0 when the assignment has no cut-off date.
+    pub cutoffdate: i64,
+}
+
+pub struct GetSubmissionStatus;
+
+impl WsFunction for GetSubmissionStatus {
+    const NAME: &'static str = "mod_assign_get_submission_status";
+    type Params = AssignIdParams;
+    type Response = SubmissionStatus;
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssignIdParams {
+    pub assignid: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubmissionStatus {
+    #[serde(default)]
+    pub lastattempt: Option<LastAttempt>,
+    #[serde(default)]
+    pub feedback: Option<Feedback>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LastAttempt {
+    #[serde(default)]
+    pub submission: Option<Submission>,
+    /// Set for group assignments.
+    #[serde(default)]
+    pub teamsubmission: Option<Submission>,
+    #[serde(default)]
+    pub locked: bool,
+    #[serde(default)]
+    pub canedit: bool,
+    #[serde(default)]
+    pub cansubmit: bool,
+    #[serde(default)]
+    pub extensionduedate: Option<i64>,
+    /// `graded` or `notgraded`, or a marking workflow state.
+    #[serde(default)]
+    pub gradingstatus: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Submission {
+    /// `new`, `draft`, `submitted` or `reopened`.
+    pub status: String,
+    #[serde(default)]
+    pub timemodified: i64,
+    #[serde(default)]
+    pub plugins: Vec<SubmissionPlugin>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubmissionPlugin {
+    /// `file`, `onlinetext`, `comments`, ...
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub fileareas: Vec<FileArea>,
+    #[serde(default)]
+    pub editorfields: Vec<EditorField>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FileArea {
+    pub area: String,
+    #[serde(default)]
+    pub files: Vec<SubmissionFile>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SubmissionFile {
+    pub filename: String,
+    #[serde(default)]
+    pub filesize: u64,
+    #[serde(default)]
+    pub fileurl: Option<String>,
+    #[serde(default)]
+    pub mimetype: Option<String>,
+    #[serde(default)]
+    pub timemodified: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EditorField {
+    pub name: String,
+    /// HTML.
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Feedback {
+    /// Grade as shown on moodle, HTML.
+    #[serde(default)]
+    pub gradefordisplay: Option<String>,
+    #[serde(default)]
+    pub gradeddate: i64,
+    #[serde(default)]
+    pub plugins: Vec<SubmissionPlugin>,
+}
+
+pub struct SaveSubmission;
+
+impl WsFunction for SaveSubmission {
+    const NAME: &'static str = "mod_assign_save_submission";
+    type Params = SaveSubmissionParams;
+    type Response = Vec<MoodleWarning>;
+}
+
+#[derive(Debug, Serialize)]
+pub struct SaveSubmissionParams {
+    pub assignmentid: u64,
+    pub plugindata: PluginData,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PluginData {
+    pub onlinetext_editor: Option<OnlineText>,
+    /// Draft item id from [`super::Client::upload`].
+    pub files_filemanager: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OnlineText {
+    /// HTML.
+    pub text: String,
+    /// 1 for HTML.
+    pub format: u8,
+    /// Draft area for files embedded in the text, 0 for none.
+    pub itemid: u64,
+}
+
+pub struct SubmitForGrading;
+
+impl WsFunction for SubmitForGrading {
+    const NAME: &'static str = "mod_assign_submit_for_grading";
+    type Params = SubmitForGradingParams;
+    type Response = Vec<MoodleWarning>;
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubmitForGradingParams {
+    pub assignmentid: u64,
+    pub acceptsubmissionstatement: bool,
+}
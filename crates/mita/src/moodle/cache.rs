@@ -0,0 +1,243 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::body::Bytes;
+use sha2::{Digest, Sha256};
+
+type Digest32 = [u8; 32];
+
+/// Successful moodle responses of recent calls, per user, so that repeated
+/// reads within a function's ttl don't reach moodle.
+///
+/// Users are told apart by a hash of their moodle token, calls by a hash of
+/// the function and its params.
+#[derive(Clone, Default)]
+pub struct ResponseCache {
+    inner: Arc<Mutex<Inner>>,
+    /// Ignore cached responses, still caching fresh ones.
+    refresh: bool,
+}
+
+#[derive(Default)]
+struct Inner {
+    users: HashMap<Digest32, HashMap<Digest32, Entry>>,
+    /// Every entry by when it expires, so that expired entries and those
+    /// closest to expiring are found without going through them all.
+    expiries: BTreeSet<(Instant, Digest32, Digest32)>,
+    /// Total length of the cached bodies.
+    bytes: usize,
+}
+
+struct Entry {
+    body: Bytes,
+    expires_at: Instant,
+}
+
+impl Entry {
+    fn expired(&self, now: Instant) -> bool {
+        self.expires_at <= now
+    }
+}
+
+impl Inner {
+    fn remove(&mut self, user: &Digest32, call: &Digest32) {
+        let Some(calls) = self.users.get_mut(user) else {
+            return;
+        };
+        if let Some(entry) = calls.remove(call) {
+            self.expiries.remove(&(entry.expires_at, *user, *call));
+            self.bytes -= entry.body.len();
+        }
+        if calls.is_empty() {
+            self.users.remove(user);
+        }
+    }
+
+    /// When the entry expiring first expires, and where it is.
+    fn first(&self) -> Option<(Instant, Digest32, Digest32)> {
+        self.expiries.first().copied()
+    }
+}
+
+/// Where one call is cached.
+pub struct CacheKey {
+    user: Digest32,
+    call: Digest32,
+}
+
+impl CacheKey {
+    pub fn new(moodle_token: &str, wsfunction: &str, params: &[(String, String)]) -> Self {
+        let mut call = Sha256::new().chain_update(wsfunction);
+        for (key, value) in params {
+            // lengths keep `a=bc` and `ab=c` apart
+            call.update((key.len() as u64).to_le_bytes());
+            call.update(key);
+            call.update((value.len() as u64).to_le_bytes());
+            call.update(value);
+        }
+        Self {
+            user: user_digest(moodle_token),
+            call: call.finalize().into(),
+        }
+    }
+}
+
+pub(super) fn user_digest(moodle_token: &str) -> Digest32 {
+    Sha256::new()
+        .chain_update("mita moodle cache")
+        .chain_update(moodle_token)
+        .finalize()
+        .into()
+}
+
+impl ResponseCache {
+    /// The same cache, but clients using it get fresh responses from moodle,
+    /// e.g. for requests with `Cache-Control: no-cache`.
+    pub fn refreshing(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            refresh: true,
+        }
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<Bytes> {
+        if self.refresh {
+            return None;
+        }
+        let now = Instant::now();
+        self.inner
+            .lock()
+            .expect("moodle cache poisoned")
+            .users
+            .get(&key.user)?
+            .get(&key.call)
+            .filter(|entry| !entry.expired(now))
+            .map(|entry| entry.body.clone())
+    }
+
+    /// Caches `body` for `ttl`, first dropping expired responses, then those
+    /// closest to expiring until it fits in `max_entries` and `max_bytes`.
+    pub fn insert(
+        &self,
+        key: CacheKey,
+        body: Bytes,
+        ttl: Duration,
+        max_entries: usize,
+        max_bytes: usize,
+    ) {
+        if max_entries == 0 || body.len() > max_bytes {
+            return;
+        }
+        let now = Instant::now();
+        let mut inner = self.inner.lock().expect("moodle cache poisoned");
+        inner.remove(&key.user, &key.call);
+        while let Some((expires_at, user, call)) = inner.first() {
+            let full = inner.expiries.len() >= max_entries || inner.bytes + body.len() > max_bytes;
+            if expires_at > now && !full {
+                break;
+            }
+            inner.remove(&user, &call);
+        }
+
+        let expires_at = now + ttl;
+        inner.expiries.insert((expires_at, key.user, key.call));
+        inner.bytes += body.len();
+        inner
+            .users
+            .entry(key.user)
+            .or_default()
+            .insert(key.call, Entry { body, expires_at });
+    }
+
+    /// Forgets every response cached for the owner of `moodle_token`, after
+    /// they changed something on moodle.
+    pub fn invalidate(&self, moodle_token: &str) {
+        let user = user_digest(moodle_token);
+        let mut inner = self.inner.lock().expect("moodle cache poisoned");
+        let calls: Vec<_> = match inner.users.get(&user) {
+            Some(calls) => calls.keys().copied().collect(),
+            None => return,
+        };
+        for call in calls {
+            inner.remove(&user, &call);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::body::Bytes;
+
+    use super::{CacheKey, ResponseCache};
+
+    fn key(token: &str, courseid: &str) -> CacheKey {
+        CacheKey::new(
+            token,
+            "core_course_get_contents",
+            &[("courseid".into(), courseid.into())],
+        )
+    }
+
+    #[test]
+    fn caches_per_user_and_params() {
+        let cache = ResponseCache::default();
+        let minute = Duration::from_secs(60);
+        cache.insert(key("a", "1"), Bytes::from("[]"), minute, 10, 100);
+
+        assert_eq!(cache.get(&key("a", "1")), Some(Bytes::from("[]")));
+        assert_eq!(cache.get(&key("a", "2")), None);
+        assert_eq!(cache.get(&key("b", "1")), None);
+        assert_eq!(cache.refreshing().get(&key("a", "1")), None);
+
+        cache.invalidate("a");
+        assert_eq!(cache.get(&key("a", "1")), None);
+    }
+
+    #[test]
+    fn expires() {
+        let cache = ResponseCache::default();
+        cache.insert(key("a", "1"), Bytes::from("[]"), Duration::ZERO, 10, 100);
+        assert_eq!(cache.get(&key("a", "1")), None);
+    }
+
+    #[test]
+    fn evicts_closest_to_expiring() {
+        let cache = ResponseCache::default();
+        let minute = Duration::from_secs(60);
+        let hour = Duration::from_secs(3600);
+        cache.insert(key("a", "1"), Bytes::from("[]"), hour, 2, 100);
+        cache.insert(key("a", "2"), Bytes::from("[]"), minute, 2, 100);
+        cache.insert(key("b", "1"), Bytes::from("[]"), hour, 2, 100);
+        assert!(cache.get(&key("a", "1")).is_some());
+        assert_eq!(cache.get(&key("a", "2")), None);
+        assert!(cache.get(&key("b", "1")).is_some());
+
+        // replacing an entry doesn't count it twice
+        cache.insert(key("b", "1"), Bytes::from("{}"), hour, 2, 100);
+        assert!(cache.get(&key("a", "1")).is_some());
+    }
+
+    #[test]
+    fn stays_within_bytes() {
+        let cache = ResponseCache::default();
+        let minute = Duration::from_secs(60);
+        let hour = Duration::from_secs(3600);
+        cache.insert(key("a", "1"), Bytes::from("x".repeat(6)), minute, 10, 10);
+        cache.insert(key("a", "2"), Bytes::from("x".repeat(4)), hour, 10, 10);
+        assert!(cache.get(&key("a", "1")).is_some());
+
+        cache.insert(key("a", "3"), Bytes::from("x".repeat(4)), hour, 10, 10);
+        assert_eq!(cache.get(&key("a", "1")), None);
+        assert!(cache.get(&key("a", "2")).is_some());
+        assert!(cache.get(&key("a", "3")).is_some());
+
+        cache.insert(key("a", "4"), Bytes::from("x".repeat(11)), hour, 10, 10);
+        assert_eq!(cache.get(&key("a", "4")), None);
+        assert!(cache.get(&key("a", "3")).is_some());
+    }
+}
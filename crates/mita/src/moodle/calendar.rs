@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+use super::function::WsFunction;
+
+pub struct GetActionEventsByTimesort;
+
+impl WsFunction for GetActionEventsByTimesort {
+    const NAME: &'static str = "core_calendar_get_action_events_by_timesort";
+    type Params = GetActionEventsByTimesortParams;
+    type Response = ActionEvents;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetActionEventsByTimesortParams {
+    pub timesortfrom: i64,
+    pub timesortto: Option<i64>,
+    /// Moodle caps this at 50.
+    pub limitnum: u32,
+    /// Id of the last event of the previous page.
+    pub aftereventid: Option<u64>,
+    pub limittononsuspendedevents: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActionEvents {
+    pub events: Vec<ActionEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActionEvent {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub modulename: Option<String>,
+    #[serde(default)]
+    pub instance: Option<u64>,
+    pub timesort: i64,
+    #[serde(default)]
+    pub course: Option<EventCourse>,
+    #[serde(default)]
+    pub action: Option<EventAction>,
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventCourse {
+    pub id: u64,
+    pub fullname: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventAction {
+    pub name: String,
+    pub actionable: bool,
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+use super::{error::MoodleWarning, function::WsFunction};
+
+pub struct GetActivitiesCompletionStatus;
+
+impl WsFunction for GetActivitiesCompletionStatus {
+    const NAME: &'static str = "core_completion_get_activities_completion_status";
+    type Params = CompletionStatusParams;
+    type Response = ActivitiesCompletionStatus;
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompletionStatusParams {
+    pub courseid: u64,
+    pub userid: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActivitiesCompletionStatus {
+    pub statuses: Vec<ActivityCompletionStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityCompletionStatus {
+    pub cmid: u64,
+    pub modname: String,
+    pub instance: u64,
+    /// Moodle's `COMPLETION_*` constants.
+    pub state: u8,
+    /// Unix timestamp, 0 when not complete.
+    pub timecompleted: i64,
+    /// 0 for none, 1 for manual and 2 for automatic.
+    pub tracking: u8,
+    /// Teacher who set the state instead of the student or moodle.
+    #[serde(default)]
+    pub overrideby: Option<u64>,
+}
+
+pub struct GetCourseCompletionStatus;
+
+impl WsFunction for GetCourseCompletionStatus {
+    const NAME: &'static str = "core_completion_get_course_completion_status";
+    type Params = CompletionStatusParams;
+    type Response = CourseCompletionStatusResponse;
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CourseCompletionStatusResponse {
+    pub completionstatus: CourseCompletionStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CourseCompletionStatus {
+    pub completed: bool,
+    /// 1 when all criteria are needed, 2 when any is enough.
+    pub aggregation: u8,
+    pub completions: Vec<CriterionCompletion>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CriterionCompletion {
+    pub title: String,
+    /// Localized, e.g. `Yes` or `No`.
+    pub status: String,
+    pub complete: bool,
+    #[serde(default)]
+    pub timecompleted: Option<i64>,
+}
+
+pub struct UpdateActivityCompletionStatusManually;
+
+impl WsFunction for UpdateActivityCompletionStatusManually {
+    const NAME: &'static str = "core_completion_update_activity_completion_status_manually";
+    type Params = UpdateActivityCompletionParams;
+    type Response = UpdateActivityCompletionResponse;
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdateActivityCompletionParams {
+    pub cmid: u64,
+    pub completed: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateActivityCompletionResponse {
+    pub status: bool,
+    #[serde(default)]
+    pub warnings: Vec<MoodleWarning>,
+}
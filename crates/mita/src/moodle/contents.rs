@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+use super::{de::int_bool, function::WsFunction};
+
+pub struct GetContents;
+
+impl WsFunction for GetContents {
+    const NAME: &'static str = "core_course_get_contents";
+    type Params = GetContentsParams;
+    type Response = Vec<Section>;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetContentsParams {
+    pub courseid: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Section {
+    pub id: u64,
+    pub name: String,
+    /// Position of the section in the course, 0 is the general section.
+    pub section: u32,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default = "visible", deserialize_with = "int_bool")]
+    pub visible: bool,
+    #[serde(default = "visible")]
+    pub uservisible: bool,
+    #[serde(default)]
+    pub modules: Vec<Module>,
+}
+
+/// A course module, i.e. an activity or resource.
+#[derive(Debug, Deserialize)]
+pub struct Module {
+    /// Course module id, `cmid` elsewhere.
+    pub id: u64,
+    pub name: String,
+    /// Id within the module's own table, e.g. the assignment id.
+    pub instance: u64,
+    /// `resource`, `url`, `page`, `assign`, `quiz`, `forum`, ...
+    pub modname: String,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "visible", deserialize_with = "int_bool")]
+    pub visible: bool,
+    #[serde(default = "visible")]
+    pub uservisible: bool,
+    /// Why the module is restricted, as HTML.
+    #[serde(default)]
+    pub availabilityinfo: Option<String>,
+    /// 0 none, 1 manual, 2 automatic.
+    #[serde(default)]
+    pub completion: u8,
+    #[serde(default)]
+    pub completiondata: Option<CompletionData>,
+    #[serde(default)]
+    pub contents: Vec<Content>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompletionData {
+    /// 0 incomplete, 1 complete, 2 complete and passed, 3 complete and failed.
+    pub state: u8,
+    #[serde(default)]
+    pub timecompleted: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Content {
+    /// `file`, `url` or `content`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub filename: String,
+    #[serde(default)]
+    pub filepath: Option<String>,
+    #[serde(default)]
+    pub filesize: u64,
+    #[serde(default)]
+    pub fileurl: Option<String>,
+    #[serde(default)]
+    pub timemodified: i64,
+    #[serde(default)]
+    pub mimetype: Option<String>,
+}
+
+fn visible() -> bool {
+    true
+}
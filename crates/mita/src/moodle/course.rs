@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use super::function::WsFunction;
+
+pub struct GetUsersCourses;
+
+impl WsFunction for GetUsersCourses {
+    const NAME: &'static str = "core_enrol_get_users_courses";
+    type Params = GetUsersCoursesParams;
+    type Response = Vec<Course>;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetUsersCoursesParams {
+    pub userid: u64,
+    pub returnusercount: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Course {
+    pub id: u64,
+    pub shortname: String,
+    pub fullname: String,
+    #[serde(default)]
+    pub category: Option<u64>,
+    #[serde(default)]
+    pub progress: Option<f64>,
+    #[serde(default)]
+    pub startdate: i64,
+    #[serde(default)]
+    pub enddate: i64,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub isfavourite: bool,
+}
+
+pub struct GetCourseModule;
+
+impl WsFunction for GetCourseModule {
+    const NAME: &'static str = "core_course_get_course_module";
+    type Params = GetCourseModuleParams;
+    type Response = CourseModuleResponse;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetCourseModuleParams {
+    pub cmid: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CourseModuleResponse {
+    pub cm: CourseModule,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CourseModule {
+    pub id: u64,
+    pub course: u64,
+    pub name: String,
+    /// `assign`, `quiz`, `forum`, ...
+    pub modname: String,
+    /// Id within the module's own table, e.g. the assignment id.
+    pub instance: u64,
+}
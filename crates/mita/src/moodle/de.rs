@@ -0,0 +1,40 @@
+//! Deserializers for Moodle's loosely typed fields.
+
+use serde::{Deserialize, Deserializer};
+
+/// Moodle returns some flags as `0`/`1` and others as booleans, sometimes
+/// the same flag differently between versions.
+pub fn int_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum IntOrBool {
+        Int(i64),
+        Bool(bool),
+    }
+
+    Ok(match IntOrBool::deserialize(deserializer)? {
+        IntOrBool::Int(i) => i != 0,
+        IntOrBool::Bool(b) => b,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Deserialize)]
+    struct Flag {
+        #[serde(deserialize_with = "super::int_bool")]
+        visible: bool,
+    }
+
+    #[test]
+    fn reads_ints_and_bools() -> eyre::Result<()> {
+        for (value, expected) in [(json!(1), true), (json!(0), false), (json!(true), true)] {
+            let flag: Flag = serde_json::from_value(json!({ "visible": value }))?;
+            assert_eq!(flag.visible, expected);
+        }
+        Ok(())
+    }
+}
@@ -8,6 +8,10 @@ pub enum MoodleError {
     Unexpected(#[from] eyre::Error),
     #[error("error from moodle api")]
     Api(#[from] MoodleApiError),
+    /// Moodle rejected the registered token, the user has to register one
+    /// again.
+    #[error("moodle no longer accepts the registered token")]
+    StaleToken,
 }
 
 #[derive(Error, Debug, Deserialize)]
@@ -15,6 +19,8 @@ pub enum MoodleError {
 pub struct MoodleApiError {
     #[serde(rename = "errorcode")]
     pub kind: MoodleApiErrorKind,
+    // login/token.php names it `error`
+    #[serde(alias = "error")]
     pub message: String,
 }
 
@@ -22,18 +28,163 @@ pub struct MoodleApiError {
 #[serde(rename_all = "lowercase")]
 pub enum MoodleApiErrorKind {
     InvalidToken,
+    InvalidLogin,
+    /// Not enrolled in the course.
+    RequireLoginError,
+    NoPermissions,
+    /// The activity is hidden or its access restrictions aren't met.
+    #[serde(rename = "notavailable")]
+    NotAvailable,
+    /// The requested course, activity, etc. doesn't exist.
+    InvalidRecord,
+    /// An uploaded file is larger than the site or activity allows.
+    MaxBytes,
+    /// The user's private files are full.
+    UserQuotaLimit,
+    /// Moodle reports submissions after the cut-off date this way, among
+    /// other reasons the submission can't be changed.
+    CouldNotSaveSubmission,
+    CouldNotSubmitForGrading,
+    /// Messaging is turned off on the site.
+    #[serde(rename = "disabled")]
+    MessagingDisabled,
+    /// Moodle has no code for this one, the message is the code.
+    #[serde(rename = "You do not have permission to send a message to this conversation")]
+    ConversationPermission,
+    /// The recipient's privacy settings or a block prevent the message.
+    UserCantBeMessaged,
+    /// Completion tracking is off for the site or the course.
+    CompletionNotEnabled,
+    /// The course has no completion criteria.
+    NoCriteriaSet,
+    /// Only activities with manual completion can be marked complete.
+    #[serde(rename = "cannotmanualctrack")]
+    NotManualCompletion,
+    SiteMaintenance,
+    ServiceNotAvailable,
     #[serde(other)]
     Unknown(String),
 }
 
+/// Some functions report failures as warnings in a successful response.
+#[derive(Debug, Deserialize)]
+pub struct MoodleWarning {
+    pub warningcode: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+impl MoodleWarning {
+    /// Fails with the first warning, as an api error of the same code.
+    pub fn check(warnings: Vec<MoodleWarning>) -> Result<(), MoodleApiError> {
+        match warnings.into_iter().next() {
+            None => Ok(()),
+            Some(warning) => Err(MoodleApiError {
+                kind: serde_json::from_value(warning.warningcode.into())
+                    .expect("unknown codes are parsed as Unknown"),
+                message: warning.message,
+            }),
+        }
+    }
+}
+
+impl MoodleApiErrorKind {
+    /// Whether moodle keeps something from the user, rather than failing.
+    /// Lists can leave such things out instead of failing as a whole.
+    pub fn is_access_restriction(&self) -> bool {
+        matches!(
+            self,
+            MoodleApiErrorKind::RequireLoginError
+                | MoodleApiErrorKind::NoPermissions
+                | MoodleApiErrorKind::NotAvailable
+        )
+    }
+}
+
 impl MoodleError {
     pub fn status(&self) -> StatusCode {
         match self {
             MoodleError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            // unlike 401, which is about mita's own auth
+            MoodleError::StaleToken => StatusCode::GONE,
             MoodleError::Api(e) => match e.kind {
                 MoodleApiErrorKind::InvalidToken => StatusCode::UNAUTHORIZED,
+                MoodleApiErrorKind::InvalidLogin => StatusCode::UNAUTHORIZED,
+                MoodleApiErrorKind::RequireLoginError => StatusCode::FORBIDDEN,
+                MoodleApiErrorKind::NoPermissions => StatusCode::FORBIDDEN,
+                MoodleApiErrorKind::NotAvailable => StatusCode::FORBIDDEN,
+                MoodleApiErrorKind::InvalidRecord => StatusCode::NOT_FOUND,
+                MoodleApiErrorKind::MaxBytes => StatusCode::PAYLOAD_TOO_LARGE,
+                MoodleApiErrorKind::UserQuotaLimit => StatusCode::PAYLOAD_TOO_LARGE,
+                MoodleApiErrorKind::CouldNotSaveSubmission => StatusCode::CONFLICT,
+                MoodleApiErrorKind::CouldNotSubmitForGrading => StatusCode::CONFLICT,
+                MoodleApiErrorKind::MessagingDisabled => StatusCode::FORBIDDEN,
+                MoodleApiErrorKind::ConversationPermission => StatusCode::FORBIDDEN,
+                MoodleApiErrorKind::UserCantBeMessaged => StatusCode::FORBIDDEN,
+                MoodleApiErrorKind::CompletionNotEnabled => StatusCode::NOT_FOUND,
+                MoodleApiErrorKind::NoCriteriaSet => StatusCode::NOT_FOUND,
+                MoodleApiErrorKind::NotManualCompletion => StatusCode::CONFLICT,
+                MoodleApiErrorKind::SiteMaintenance => StatusCode::SERVICE_UNAVAILABLE,
+                MoodleApiErrorKind::ServiceNotAvailable => StatusCode::SERVICE_UNAVAILABLE,
                 MoodleApiErrorKind::Unknown(_) => StatusCode::INTERNAL_SERVER_ERROR,
             },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    use super::{MoodleApiError, MoodleApiErrorKind, MoodleError, MoodleWarning};
+
+    #[test]
+    fn messaging_errors_are_forbidden() {
+        for code in [
+            "disabled",
+            "You do not have permission to send a message to this conversation",
+        ] {
+            let e: MoodleApiError = serde_json::from_value(json!({
+                "errorcode": code,
+                "exception": "moodle_exception",
+                "message": code,
+            }))
+            .unwrap();
+            assert_eq!(MoodleError::from(e).status(), StatusCode::FORBIDDEN);
+        }
+    }
+
+    #[test]
+    fn only_restrictions_are_access_restrictions() {
+        for (code, restricted) in [
+            ("requireloginerror", true),
+            ("nopermissions", true),
+            ("notavailable", true),
+            ("invalidtoken", false),
+            ("sitemaintenance", false),
+            ("dmlreadexception", false),
+        ] {
+            let kind: MoodleApiErrorKind = serde_json::from_value(json!(code)).unwrap();
+            assert_eq!(kind.is_access_restriction(), restricted, "{code}");
+        }
+    }
+
+    #[test]
+    fn warnings_become_api_errors() {
+        assert!(MoodleWarning::check(vec![]).is_ok());
+
+        let res = MoodleWarning::check(vec![MoodleWarning {
+            warningcode: "couldnotsavesubmission".into(),
+            message: "This assignment is not accepting submissions".into(),
+        }]);
+
+        claims::assert_matches!(
+            res,
+            Err(MoodleApiError {
+                kind: MoodleApiErrorKind::CouldNotSaveSubmission,
+                ..
+            })
+        );
+    }
+}
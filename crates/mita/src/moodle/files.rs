@@ -0,0 +1,100 @@
+use axum::body::Bytes;
+use serde::Deserialize;
+use url::Url;
+
+/// A file to upload with [`super::Client::upload`].
+#[derive(Debug)]
+pub struct UploadFile {
+    pub filename: String,
+    pub content: Bytes,
+}
+
+/// An entry of `webservice/upload.php`'s response.
+#[derive(Debug, Deserialize)]
+pub struct UploadedFile {
+    pub itemid: u64,
+    pub filename: String,
+}
+
+/// Turns a file url handed out by the moodle at `base` into one that accepts
+/// a web service token, or `None` if it isn't a file of that moodle.
+pub fn pluginfile_url(base: &Url, url: &str) -> Option<Url> {
+    let url = Url::parse(url).ok()?;
+    if url.scheme() != base.scheme()
+        || url.host_str() != base.host_str()
+        || url.port_or_known_default() != base.port_or_known_default()
+    {
+        return None;
+    }
+
+    // moodle may live in a subdirectory
+    let path = url.path().strip_prefix(base.path().trim_end_matches('/'))?;
+    let file = path
+        .strip_prefix("/webservice/pluginfile.php/")
+        .or_else(|| path.strip_prefix("/pluginfile.php/"))?;
+
+    let mut file_url = base
+        .join("webservice/pluginfile.php/")
+        .ok()?
+        .join(file)
+        .ok()?;
+    let query: Vec<_> = url
+        .query_pairs()
+        .filter(|(key, _)| key != "token" && key != "wstoken")
+        .collect();
+    if !query.is_empty() {
+        file_url.query_pairs_mut().extend_pairs(query);
+    }
+
+    Some(file_url)
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::pluginfile_url;
+
+    fn base() -> Url {
+        "https://e-learning.hcmut.edu.vn".parse().unwrap()
+    }
+
+    #[test]
+    fn accepts_files_of_moodle() {
+        let url = pluginfile_url(
+            &base(),
+            "https://e-learning.hcmut.edu.vn/webservice/pluginfile.php/1/mod_resource/content/1/a%20b.pdf?forcedownload=1&token=stolen",
+        );
+        assert_eq!(
+            url.unwrap().as_str(),
+            "https://e-learning.hcmut.edu.vn/webservice/pluginfile.php/1/mod_resource/content/1/a%20b.pdf?forcedownload=1"
+        );
+    }
+
+    #[test]
+    fn rewrites_browser_urls() {
+        let url = pluginfile_url(
+            &base(),
+            "https://e-learning.hcmut.edu.vn/pluginfile.php/1/mod_folder/content/0/x.zip",
+        );
+        assert_eq!(
+            url.unwrap().as_str(),
+            "https://e-learning.hcmut.edu.vn/webservice/pluginfile.php/1/mod_folder/content/0/x.zip"
+        );
+    }
+
+    #[test]
+    fn rejects_other_urls() {
+        for url in [
+            "https://evil.com/webservice/pluginfile.php/1/a.pdf",
+            "http://e-learning.hcmut.edu.vn/webservice/pluginfile.php/1/a.pdf",
+            "https://e-learning.hcmut.edu.vn:8443/webservice/pluginfile.php/1/a.pdf",
+            "https://e-learning.hcmut.edu.vn/webservice/rest/server.php",
+            "https://e-learning.hcmut.edu.vn/webservice/pluginfile.php/../rest/server.php",
+            "https://e-learning.hcmut.edu.vn.evil.com/pluginfile.php/1/a.pdf",
+            "not a url",
+        ] {
+            assert_eq!(pluginfile_url(&base(), url), None, "{url}");
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+use super::function::WsFunction;
+
+pub struct GetForumsByCourses;
+
+impl WsFunction for GetForumsByCourses {
+    const NAME: &'static str = "mod_forum_get_forums_by_courses";
+    type Params = GetForumsByCoursesParams;
+    type Response = Vec<Forum>;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetForumsByCoursesParams {
+    pub courseids: Vec<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Forum {
+    pub id: u64,
+    pub course: u64,
+    /// `news` for a course's announcements forum, `general`, `qanda`, ...
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub name: String,
+    /// HTML.
+    #[serde(default)]
+    pub intro: String,
+    pub cmid: u64,
+    #[serde(default)]
+    pub numdiscussions: Option<u32>,
+    #[serde(default)]
+    pub unreadpostscount: Option<u32>,
+}
+
+pub struct GetForumDiscussions;
+
+impl WsFunction for GetForumDiscussions {
+    const NAME: &'static str = "mod_forum_get_forum_discussions";
+    type Params = GetForumDiscussionsParams;
+    type Response = ForumDiscussions;
+}
+
+/// Moodle's default discussion order, most recently active first.
+pub const SORT_DEFAULT: i32 = -1;
+/// Moodle's `discussion_list::SORTORDER_CREATED_DESC`, newest first.
+pub const SORT_CREATED_DESC: i32 = 3;
+
+#[derive(Debug, Serialize)]
+pub struct GetForumDiscussionsParams {
+    pub forumid: u64,
+    /// [`SORT_DEFAULT`] or one of moodle's `discussion_list::SORTORDER_*`
+    /// constants.
+    pub sortorder: i32,
+    pub page: u32,
+    pub perpage: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForumDiscussions {
+    pub discussions: Vec<Discussion>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Discussion {
+    /// Id of the first post.
+    pub id: u64,
+    /// Id of the discussion.
+    pub discussion: u64,
+    pub name: String,
+    pub subject: String,
+    /// HTML.
+    pub message: String,
+    pub userid: u64,
+    #[serde(default)]
+    pub userfullname: Option<String>,
+    pub created: i64,
+    /// Last post in the discussion.
+    pub timemodified: i64,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub locked: bool,
+    #[serde(default)]
+    pub numreplies: u32,
+    #[serde(default)]
+    pub numunread: u32,
+}
+
+pub struct GetDiscussionPosts;
+
+impl WsFunction for GetDiscussionPosts {
+    const NAME: &'static str = "mod_forum_get_discussion_posts";
+    type Params = GetDiscussionPostsParams;
+    type Response = DiscussionPosts;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetDiscussionPostsParams {
+    pub discussionid: u64,
+    /// `created`, `modified` or `id`.
+    pub sortby: &'static str,
+    pub sortdirection: &'static str,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiscussionPosts {
+    pub posts: Vec<Post>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Post {
+    pub id: u64,
+    pub discussionid: u64,
+    #[serde(default)]
+    pub parentid: Option<u64>,
+    pub subject: String,
+    /// HTML.
+    pub message: String,
+    pub author: PostAuthor,
+    pub timecreated: i64,
+    #[serde(default)]
+    pub isdeleted: bool,
+    #[serde(default)]
+    pub attachments: Vec<PostAttachment>,
+}
+
+/// Fields are missing when the viewer may not see the author, e.g. in
+/// anonymous forums.
+#[derive(Debug, Deserialize)]
+pub struct PostAuthor {
+    #[serde(default)]
+    pub id: Option<u64>,
+    #[serde(default)]
+    pub fullname: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostAttachment {
+    pub filename: String,
+    /// Pluginfile url.
+    pub url: String,
+    #[serde(default)]
+    pub filesize: u64,
+    #[serde(default)]
+    pub mimetype: Option<String>,
+}
@@ -0,0 +1,141 @@
+use eyre::Context;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+/// A Moodle web service function, called through [`super::Client::call`].
+///
+/// # Example
+///
+/// ```
+/// use mita::moodle::function::WsFunction;
+/// use serde::{Deserialize, Serialize};
+///
+/// struct GetCoursesByField;
+///
+/// #[derive(Serialize)]
+/// struct Params {
+///     field: String,
+///     value: String,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Response {
+///     courses: Vec<serde_json::Value>,
+/// }
+///
+/// impl WsFunction for GetCoursesByField {
+///     const NAME: &'static str = "core_course_get_courses_by_field";
+///     type Params = Params;
+///     type Response = Response;
+/// }
+/// ```
+pub trait WsFunction {
+    /// Value of the `wsfunction` form field.
+    const NAME: &'static str;
+    type Params: Serialize + Sync;
+    type Response: DeserializeOwned + Send;
+}
+
+/// Encodes `params` the way Moodle's REST server reads them: arrays and
+/// objects are flattened into `key[0][field]=value` pairs, booleans become
+/// `1`/`0` and `null`s are left out.
+pub fn encode_form<T: Serialize>(params: &T) -> eyre::Result<Vec<(String, String)>> {
+    let mut form = Vec::new();
+    match serde_json::to_value(params).wrap_err("error serializing moodle params")? {
+        Value::Null => {}
+        Value::Object(map) => {
+            for (key, value) in map {
+                flatten(key, value, &mut form);
+            }
+        }
+        _ => eyre::bail!("moodle params must be a struct or a map"),
+    }
+    Ok(form)
+}
+
+fn flatten(key: String, value: Value, form: &mut Vec<(String, String)>) {
+    match value {
+        Value::Null => {}
+        Value::Bool(b) => form.push((key, if b { "1" } else { "0" }.to_string())),
+        Value::Number(n) => form.push((key, n.to_string())),
+        Value::String(s) => form.push((key, s)),
+        Value::Array(items) => {
+            for (i, item) in items.into_iter().enumerate() {
+                flatten(format!("{key}[{i}]"), item, form);
+            }
+        }
+        Value::Object(map) => {
+            for (field, item) in map {
+                flatten(format!("{key}[{field}]"), item, form);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+    use serde_json::json;
+
+    use super::encode_form;
+
+    fn pairs(form: &[(&str, &str)]) -> Vec<(String, String)> {
+        form.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn unit_params_are_empty() -> eyre::Result<()> {
+        assert_eq!(encode_form(&())?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn scalars() -> eyre::Result<()> {
+        #[derive(Serialize)]
+        struct Params {
+            userid: u64,
+            returnusercount: bool,
+            name: &'static str,
+            limit: Option<u32>,
+        }
+
+        let form = encode_form(&Params {
+            userid: 3,
+            returnusercount: false,
+            name: "a b",
+            limit: None,
+        })?;
+
+        assert_eq!(
+            form,
+            pairs(&[("name", "a b"), ("returnusercount", "0"), ("userid", "3")])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn nested_arrays_and_objects() -> eyre::Result<()> {
+        let form = encode_form(&json!({
+            "courseids": [4, 2],
+            "options": [{ "name": "excludemodules", "value": true }],
+        }))?;
+
+        assert_eq!(
+            form,
+            pairs(&[
+                ("courseids[0]", "4"),
+                ("courseids[1]", "2"),
+                ("options[0][name]", "excludemodules"),
+                ("options[0][value]", "1"),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_top_level_scalar() {
+        let _ = claims::assert_err!(encode_form(&1));
+    }
+}
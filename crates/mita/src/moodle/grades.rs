@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+use super::function::WsFunction;
+
+pub struct GetGradeItems;
+
+impl WsFunction for GetGradeItems {
+    const NAME: &'static str = "gradereport_user_get_grade_items";
+    type Params = GetGradeItemsParams;
+    type Response = UserGrades;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetGradeItemsParams {
+    pub courseid: u64,
+    pub userid: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserGrades {
+    pub usergrades: Vec<UserGrade>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserGrade {
+    pub courseid: u64,
+    pub gradeitems: Vec<GradeItem>,
+}
+
+/// Which fields are present depends on the grade report settings of the
+/// course, hence the defaults.
+#[derive(Debug, Deserialize)]
+pub struct GradeItem {
+    pub id: u64,
+    /// `None` for the course total.
+    pub itemname: Option<String>,
+    /// `course`, `category`, `mod` or `manual`.
+    pub itemtype: String,
+    pub itemmodule: Option<String>,
+    #[serde(default)]
+    pub cmid: Option<u64>,
+    /// Weight in the parent category, between 0 and 1.
+    #[serde(default)]
+    pub weightraw: Option<f64>,
+    #[serde(default)]
+    pub graderaw: Option<f64>,
+    #[serde(default)]
+    pub gradeformatted: Option<String>,
+    #[serde(default)]
+    pub gradedategraded: Option<i64>,
+    #[serde(default)]
+    pub grademin: Option<f64>,
+    #[serde(default)]
+    pub grademax: Option<f64>,
+    #[serde(default)]
+    pub feedback: Option<String>,
+    #[serde(default)]
+    pub gradeishidden: bool,
+}
+
+pub struct GetCourseGrades;
+
+impl WsFunction for GetCourseGrades {
+    const NAME: &'static str = "gradereport_overview_get_course_grades";
+    type Params = GetCourseGradesParams;
+    type Response = CourseGrades;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetCourseGradesParams {
+    pub userid: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CourseGrades {
+    pub grades: Vec<CourseGrade>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CourseGrade {
+    pub courseid: u64,
+    /// Formatted as configured for the course, `-` when not graded.
+    pub grade: String,
+    /// Decimal number as a string, e.g. `8.50000`.
+    #[serde(default)]
+    pub rawgrade: Option<String>,
+    #[serde(default)]
+    pub rank: Option<u32>,
+}
@@ -11,16 +11,23 @@ pub trait MoodleJson {
 #[async_trait::async_trait]
 impl MoodleJson for reqwest::Response {
     async fn moodle_json<T: DeserializeOwned>(self) -> Result<T, MoodleError> {
-        #[derive(Debug, Deserialize)]
-        #[serde(untagged)]
-        pub enum MoodleApiResponse<R> {
-            Err(MoodleApiError),
-            Ok(R),
-        }
-        match self.json().await.wrap_err("error deserializing body")? {
-            MoodleApiResponse::Ok(r) => Ok(r),
-            MoodleApiResponse::Err(e) => Err(e.into()),
-        }
+        let body = self.bytes().await.wrap_err("error reading body")?;
+        parse(&body)
+    }
+}
+
+/// Parses a moodle response body, which is either the expected json or an
+/// error object.
+pub fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, MoodleError> {
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    pub enum MoodleApiResponse<R> {
+        Err(MoodleApiError),
+        Ok(R),
+    }
+    match serde_json::from_slice(body).wrap_err("error deserializing body")? {
+        MoodleApiResponse::Ok(r) => Ok(r),
+        MoodleApiResponse::Err(e) => Err(e.into()),
     }
 }
 
@@ -29,7 +36,11 @@ mod tests {
     use serde_json::json;
     use wiremock::{matchers::any, Mock, MockServer, ResponseTemplate};
 
-    use crate::moodle::{error::MoodleError, json_response::MoodleJson, InfoResponse};
+    use crate::moodle::{
+        error::{MoodleApiError, MoodleApiErrorKind, MoodleError},
+        json_response::MoodleJson,
+        site_info::InfoResponse,
+    };
 
     #[tokio::test]
     async fn deserialize_invalid_token() -> eyre::Result<()> {
@@ -62,6 +73,8 @@ mod tests {
         Mock::given(any())
             .respond_with(ResponseTemplate::new(200).set_body_json(json!({
                 "fullname": "hoho",
+                "userid": 2,
+                "username": "2012345",
             })))
             .expect(1)
             .mount(&mock)
@@ -77,6 +90,38 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn deserialize_invalid_login() -> eyre::Result<()> {
+        let mock = MockServer::start().await;
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": "Invalid login, please try again",
+                "errorcode": "invalidlogin",
+                "stacktrace": null,
+                "debuginfo": null,
+                "reproductionlink": null,
+            })))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        let res = reqwest::get(&mock.uri())
+            .await?
+            .moodle_json::<InfoResponse>()
+            .await;
+
+        claims::assert_matches!(
+            res,
+            Err(MoodleError::Api(MoodleApiError {
+                kind: MoodleApiErrorKind::InvalidLogin,
+                ..
+            }))
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn doesnt_crash_on_unexpected_code() -> eyre::Result<()> {
         let mock = MockServer::start().await;
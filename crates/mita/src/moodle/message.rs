@@ -0,0 +1,236 @@
+use serde::{Deserialize, Serialize};
+
+use super::{error::MoodleWarning, function::WsFunction};
+
+pub struct GetPopupNotifications;
+
+impl WsFunction for GetPopupNotifications {
+    const NAME: &'static str = "message_popup_get_popup_notifications";
+    type Params = GetPopupNotificationsParams;
+    type Response = PopupNotifications;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetPopupNotificationsParams {
+    pub useridto: u64,
+    pub newestfirst: bool,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PopupNotifications {
+    pub notifications: Vec<PopupNotification>,
+    pub unreadcount: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PopupNotification {
+    pub id: u64,
+    /// The noreply user for notifications sent by moodle itself.
+    pub useridfrom: i64,
+    pub subject: String,
+    /// HTML.
+    #[serde(default)]
+    pub fullmessagehtml: Option<String>,
+    #[serde(default)]
+    pub smallmessage: Option<String>,
+    /// The page the notification is about.
+    #[serde(default)]
+    pub contexturl: Option<String>,
+    #[serde(default)]
+    pub contexturlname: Option<String>,
+    /// Component that sent the notification, e.g. `mod_forum`.
+    #[serde(default)]
+    pub component: Option<String>,
+    #[serde(default)]
+    pub eventtype: Option<String>,
+    pub timecreated: i64,
+    #[serde(default)]
+    pub timeread: Option<i64>,
+    #[serde(default)]
+    pub read: bool,
+}
+
+pub struct MarkNotificationRead;
+
+impl WsFunction for MarkNotificationRead {
+    const NAME: &'static str = "core_message_mark_notification_read";
+    type Params = MarkNotificationReadParams;
+    type Response = MarkNotificationReadResponse;
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarkNotificationReadParams {
+    pub notificationid: u64,
+    pub timeread: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MarkNotificationReadResponse {
+    #[serde(default)]
+    pub warnings: Vec<MoodleWarning>,
+}
+
+pub struct MarkAllNotificationsRead;
+
+impl WsFunction for MarkAllNotificationsRead {
+    const NAME: &'static str = "core_message_mark_all_notifications_as_read";
+    type Params = MarkAllNotificationsReadParams;
+    type Response = bool;
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarkAllNotificationsReadParams {
+    pub useridto: u64,
+}
+
+pub struct GetConversations;
+
+impl WsFunction for GetConversations {
+    const NAME: &'static str = "core_message_get_conversations";
+    type Params = GetConversationsParams;
+    type Response = Conversations;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetConversationsParams {
+    pub userid: u64,
+    pub limitfrom: u32,
+    /// 0 for no limit.
+    pub limitnum: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Conversations {
+    pub conversations: Vec<Conversation>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Conversation {
+    pub id: u64,
+    /// Only set for group conversations.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// 1 for private, 2 for group and 3 for self conversations.
+    #[serde(rename = "type")]
+    pub kind: u8,
+    #[serde(default)]
+    pub membercount: u32,
+    #[serde(default)]
+    pub isfavourite: bool,
+    #[serde(default)]
+    pub ismuted: bool,
+    #[serde(default)]
+    pub isread: bool,
+    #[serde(default)]
+    pub unreadcount: Option<u32>,
+    /// The other members, up to a few of them.
+    #[serde(default)]
+    pub members: Vec<ConversationMember>,
+    /// The latest message.
+    #[serde(default)]
+    pub messages: Vec<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConversationMember {
+    pub id: u64,
+    pub fullname: String,
+    #[serde(default)]
+    pub profileimageurl: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Message {
+    pub id: u64,
+    pub useridfrom: u64,
+    /// HTML.
+    pub text: String,
+    pub timecreated: i64,
+}
+
+pub struct GetConversationMessages;
+
+impl WsFunction for GetConversationMessages {
+    const NAME: &'static str = "core_message_get_conversation_messages";
+    type Params = GetConversationMessagesParams;
+    type Response = ConversationMessages;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetConversationMessagesParams {
+    pub currentuserid: u64,
+    pub convid: u64,
+    pub newest: bool,
+    pub limitfrom: u32,
+    /// 0 for no limit.
+    pub limitnum: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConversationMessages {
+    pub id: u64,
+    #[serde(default)]
+    pub members: Vec<ConversationMember>,
+    pub messages: Vec<Message>,
+}
+
+/// Moodle's `FORMAT_PLAIN`, text is escaped rather than rendered.
+pub const FORMAT_PLAIN: u8 = 2;
+
+pub struct SendMessagesToConversation;
+
+impl WsFunction for SendMessagesToConversation {
+    const NAME: &'static str = "core_message_send_messages_to_conversation";
+    type Params = SendMessagesToConversationParams;
+    type Response = Vec<Message>;
+}
+
+#[derive(Debug, Serialize)]
+pub struct SendMessagesToConversationParams {
+    pub conversationid: u64,
+    pub messages: Vec<NewMessage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NewMessage {
+    pub text: String,
+    pub textformat: u8,
+}
+
+pub struct SendInstantMessages;
+
+impl WsFunction for SendInstantMessages {
+    const NAME: &'static str = "core_message_send_instant_messages";
+    type Params = SendInstantMessagesParams;
+    type Response = Vec<SentInstantMessage>;
+}
+
+#[derive(Debug, Serialize)]
+pub struct SendInstantMessagesParams {
+    pub messages: Vec<InstantMessage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstantMessage {
+    pub touserid: u64,
+    pub text: String,
+    pub textformat: u8,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SentInstantMessage {
+    /// -1 when the message wasn't sent.
+    pub msgid: i64,
+    /// Why the message wasn't sent, localized.
+    #[serde(default)]
+    pub errormessage: Option<String>,
+    /// HTML.
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub timecreated: Option<i64>,
+    #[serde(default)]
+    pub useridfrom: Option<u64>,
+}
@@ -1,64 +1,403 @@
+pub mod assign;
+pub mod cache;
+pub mod calendar;
+pub mod completion;
+pub mod contents;
+pub mod course;
+pub mod de;
 pub mod error;
+pub mod files;
+pub mod forum;
+pub mod function;
+pub mod grades;
 pub mod json_response;
+pub mod message;
+pub mod quiz;
+pub mod site_info;
 pub mod token;
+pub mod validation;
+
+use std::time::Duration;
 
 use eyre::WrapErr;
-use secrecy::ExposeSecret;
+use reqwest::{
+    header::{HeaderValue, RANGE},
+    multipart::{Form, Part},
+};
+use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 use tracing::{info_span, Instrument};
 
-use crate::config::MoodleConfig;
+use crate::{clock::now, config::MoodleConfig, secret_store::DynSecretStore};
+
+/// Longest time a web service call or upload may take, downloads aren't
+/// bound by it.
+const CALL_TIMEOUT: Duration = Duration::from_secs(30);
 
-use self::{error::MoodleError, json_response::MoodleJson, token::MoodleToken};
+use self::{
+    cache::{CacheKey, ResponseCache},
+    course::{Course, GetUsersCourses, GetUsersCoursesParams},
+    error::{MoodleApiError, MoodleApiErrorKind, MoodleError},
+    files::{UploadFile, UploadedFile},
+    function::WsFunction,
+    json_response::MoodleJson,
+    site_info::{GetSiteInfo, InfoResponse},
+    token::MoodleToken,
+    validation::{Validation, Validations},
+};
 
 #[derive(Clone)]
 pub struct Client {
     http_client: reqwest::Client,
     config: &'static MoodleConfig,
     moodle_token: MoodleToken,
+    info: InfoResponse,
+    /// `info` was fetched while building the client, rather than remembered
+    /// from an earlier validation.
+    fresh_info: bool,
+    cache: ResponseCache,
+    validations: Validations,
+    /// Where validations of the token are also recorded, `None` when the
+    /// token isn't the one registered, e.g. a feed token's copy.
+    secret_store: Option<DynSecretStore>,
 }
 
 impl Client {
-    #[tracing::instrument(skip(http_client, config, moodle_token))]
+    /// Builds a client for a registered token, only asking moodle whether it
+    /// still accepts the token once the last validation is older than the
+    /// configured interval.
+    #[tracing::instrument(skip(
+        http_client,
+        config,
+        moodle_token,
+        cache,
+        validations,
+        secret_store
+    ))]
     pub async fn new(
         http_client: &reqwest::Client,
         config: &'static MoodleConfig,
         moodle_token: MoodleToken,
+        cache: ResponseCache,
+        validations: Validations,
+        secret_store: Option<DynSecretStore>,
+    ) -> Result<Self, MoodleError> {
+        let mut client = Self {
+            http_client: http_client.clone(),
+            config,
+            moodle_token,
+            info: InfoResponse::default(),
+            fresh_info: false,
+            cache,
+            validations,
+            secret_store,
+        };
+
+        match client.validations.get(
+            client.moodle_token.expose_secret(),
+            client.validation_interval(),
+        ) {
+            Some(Validation::Valid(info)) => client.info = info,
+            Some(Validation::Stale) => return Err(MoodleError::StaleToken),
+            None => {
+                client.info = client.get_info().await?;
+                client.fresh_info = true;
+                client.record(Validation::Valid(client.info.clone())).await;
+            }
+        }
+
+        Ok(client)
+    }
+
+    /// Builds a client for a token about to be registered, always validating
+    /// it with moodle. A rejected token is reported as is, not as stale.
+    #[tracing::instrument(skip(http_client, config, moodle_token, cache, validations))]
+    pub async fn validate(
+        http_client: &reqwest::Client,
+        config: &'static MoodleConfig,
+        moodle_token: MoodleToken,
+        cache: ResponseCache,
+        validations: Validations,
     ) -> Result<Self, MoodleError> {
-        let client = Self {
+        let mut client = Self {
             http_client: http_client.clone(),
             config,
             moodle_token,
+            info: InfoResponse::default(),
+            fresh_info: false,
+            cache,
+            validations,
+            // the caller registers the token along with its validation
+            secret_store: None,
         };
 
-        // validate token by sending a request to moodle
-        client.get_info().await?;
+        client.info = client.call_moodle::<GetSiteInfo>(&()).await?;
+        client.fresh_info = true;
+        client.record(Validation::Valid(client.info.clone())).await;
 
         Ok(client)
     }
 
+    fn validation_interval(&self) -> Duration {
+        Duration::from_secs(self.config.validation_interval)
+    }
+
+    /// Remembers the validation, and records it in the secret store so that
+    /// it outlives the process and shows in the token status.
+    async fn record(&self, validation: Validation) {
+        if let Some(secret_store) = &self.secret_store {
+            let res = match &validation {
+                Validation::Valid(_) => secret_store.record_validation(now()).await,
+                Validation::Stale => secret_store.mark_stale().await,
+            };
+            // moodle's answer stands either way
+            if let Err(error) = res {
+                tracing::warn!(?error, "error recording token validation");
+            }
+        }
+        self.validations.record(
+            self.moodle_token.expose_secret(),
+            validation,
+            self.validation_interval(),
+        );
+    }
+
+    /// Marks the token stale once moodle stops accepting it, so that
+    /// following requests fail without asking moodle.
+    async fn mark_stale_on_invalid_token<T>(
+        &self,
+        res: Result<T, MoodleError>,
+    ) -> Result<T, MoodleError> {
+        match res {
+            Err(MoodleError::Api(MoodleApiError {
+                kind: MoodleApiErrorKind::InvalidToken,
+                ..
+            })) => {
+                self.record(Validation::Stale).await;
+                Err(MoodleError::StaleToken)
+            }
+            res => res,
+        }
+    }
+
+    /// Exchanges moodle credentials for a token of the mobile app service.
+    /// The password is only sent to moodle.
+    #[tracing::instrument(skip(http_client, config, password))]
+    pub async fn login(
+        http_client: &reqwest::Client,
+        config: &'static MoodleConfig,
+        username: &str,
+        password: &Secret<String>,
+    ) -> Result<MoodleToken, MoodleError> {
+        let url = config
+            .url
+            .join("login/token.php")
+            .wrap_err("invalid moodle url")?;
+
+        let res = http_client
+            .post(url)
+            .form(&[
+                ("username", username),
+                ("password", password.expose_secret()),
+                ("service", "moodle_mobile_app"),
+            ])
+            .send()
+            .instrument(info_span!("logging into moodle"))
+            .await
+            .wrap_err("error sending request to moodle")?;
+
+        #[derive(Deserialize)]
+        struct Response {
+            token: Secret<String>,
+        }
+
+        let res: Response = res.moodle_json().await?;
+
+        Ok(res
+            .token
+            .expose_secret()
+            .parse()
+            .wrap_err("malformed token from moodle")?)
+    }
+
+    /// Site info as moodle has it now, only asking moodle again when the
+    /// client was built from an earlier validation.
+    pub async fn fresh_info(&self) -> Result<InfoResponse, MoodleError> {
+        match self.fresh_info {
+            true => Ok(self.info.clone()),
+            false => self.get_info().await,
+        }
+    }
+
     #[tracing::instrument(skip(self))]
     pub async fn get_info(&self) -> Result<InfoResponse, MoodleError> {
+        self.call::<GetSiteInfo>(&()).await
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_courses(&self) -> Result<Vec<Course>, MoodleError> {
+        self.call::<GetUsersCourses>(&GetUsersCoursesParams {
+            userid: self.info.userid,
+            returnusercount: false,
+        })
+        .await
+    }
+
+    #[tracing::instrument(skip(self, params), fields(wsfunction = F::NAME))]
+    pub async fn call<F: WsFunction>(
+        &self,
+        params: &F::Params,
+    ) -> Result<F::Response, MoodleError> {
+        let res = self.call_moodle::<F>(params).await;
+        self.mark_stale_on_invalid_token(res).await
+    }
+
+    async fn call_moodle<F: WsFunction>(
+        &self,
+        params: &F::Params,
+    ) -> Result<F::Response, MoodleError> {
+        let mut form = vec![
+            ("wstoken".into(), self.moodle_token.expose_secret().clone()),
+            ("wsfunction".into(), F::NAME.into()),
+            ("moodlewsrestformat".into(), "json".into()),
+        ];
+        let params = function::encode_form(params)?;
+
+        let ttl = self.config.cache.ttls.get(F::NAME).copied().unwrap_or(0);
+        let key =
+            (ttl != 0).then(|| CacheKey::new(self.moodle_token.expose_secret(), F::NAME, &params));
+        if let Some(body) = key.as_ref().and_then(|key| self.cache.get(key)) {
+            tracing::debug!("cached moodle response");
+            return json_response::parse(&body);
+        }
+
+        form.extend(params);
         let res = self
             .http_client
             .post(self.url()?)
-            .form(&[
-                ("wstoken", self.moodle_token.expose_secret().as_str()),
-                ("wsfunction", "core_webservice_get_site_info"),
-                ("moodlewsrestformat", "json"),
-            ])
+            .form(&form)
+            .timeout(CALL_TIMEOUT)
+            .send()
+            .instrument(info_span!("calling moodle function", wsfunction = F::NAME))
+            .await
+            .wrap_err("error sending request to moodle")?;
+
+        let body = res
+            .bytes()
+            .await
+            .wrap_err("error reading moodle response")?;
+        let response = json_response::parse(&body)?;
+        // errors aren't cached, they may go away
+        if let Some(key) = key {
+            self.cache.insert(
+                key,
+                body,
+                Duration::from_secs(ttl),
+                self.config.cache.max_entries,
+                self.config.cache.max_bytes,
+            );
+        }
+
+        Ok(response)
+    }
+
+    /// Drops the user's cached responses. Called after changing something on
+    /// moodle, so that the change shows up right away.
+    pub fn invalidate_cache(&self) {
+        self.cache.invalidate(self.moodle_token.expose_secret());
+    }
+
+    /// Uploads files into a new draft area, returning its item id for
+    /// functions that take files, e.g. `mod_assign_save_submission`.
+    #[tracing::instrument(skip(self, files), fields(files = files.len()))]
+    pub async fn upload(&self, files: &[UploadFile]) -> Result<u64, MoodleError> {
+        let url = self
+            .config
+            .url
+            .join("webservice/upload.php")
+            .wrap_err("invalid moodle url")?;
+
+        let mut form = Form::new()
+            .text("token", self.moodle_token.expose_secret().to_owned())
+            .text("filearea", "draft")
+            .text("itemid", "0");
+        for (i, file) in files.iter().enumerate() {
+            form = form.part(
+                format!("file_{}", i + 1),
+                Part::stream(file.content.clone()).file_name(file.filename.clone()),
+            );
+        }
+
+        let res = self
+            .http_client
+            .post(url)
+            .multipart(form)
+            .timeout(CALL_TIMEOUT)
             .send()
-            .instrument(info_span!("getting moodle info"))
+            .instrument(info_span!("uploading files to moodle"))
             .await
             .wrap_err("error sending request to moodle")?;
 
-        res.moodle_json().await
+        let uploaded: Vec<UploadedFile> = self
+            .mark_stale_on_invalid_token(res.moodle_json().await)
+            .await?;
+
+        Ok(uploaded
+            .first()
+            .ok_or_else(|| eyre::eyre!("moodle didn't store any file"))?
+            .itemid)
+    }
+
+    /// See [`files::pluginfile_url`].
+    pub fn pluginfile_url(&self, url: &str) -> Option<url::Url> {
+        files::pluginfile_url(&self.config.url, url)
+    }
+
+    /// Starts downloading a file from [`Client::pluginfile_url`], leaving the
+    /// body to be streamed by the caller.
+    #[tracing::instrument(skip(self, range))]
+    pub async fn download(
+        &self,
+        url: &url::Url,
+        range: Option<&HeaderValue>,
+    ) -> Result<reqwest::Response, MoodleError> {
+        let mut url = url.clone();
+        url.query_pairs_mut()
+            .append_pair("token", self.moodle_token.expose_secret());
+
+        let mut req = self.http_client.get(url);
+        if let Some(range) = range {
+            req = req.header(RANGE, range);
+        }
+
+        Ok(req
+            .send()
+            .instrument(info_span!("downloading file from moodle"))
+            .await
+            // the url contains the token
+            .map_err(reqwest::Error::without_url)
+            .wrap_err("error sending request to moodle")?)
     }
 
     pub fn token(&self) -> &MoodleToken {
         &self.moodle_token
     }
 
+    /// Id of the moodle user owning the token.
+    pub fn user_id(&self) -> u64 {
+        self.info.userid
+    }
+
+    /// Login name of the moodle user owning the token.
+    pub fn username(&self) -> &str {
+        &self.info.username
+    }
+
+    /// Root of the moodle site, e.g. `https://e-learning.hcmut.edu.vn`.
+    pub fn base_url(&self) -> &url::Url {
+        &self.config.url
+    }
+
     pub fn url(&self) -> eyre::Result<url::Url> {
         self.config
             .url
@@ -66,8 +405,3 @@ impl Client {
             .wrap_err("invalid moodle url")
     }
 }
-
-#[derive(Debug, Deserialize)]
-pub struct InfoResponse {
-    pub fullname: String,
-}
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+
+use super::function::WsFunction;
+
+pub struct GetQuizzesByCourses;
+
+impl WsFunction for GetQuizzesByCourses {
+    const NAME: &'static str = "mod_quiz_get_quizzes_by_courses";
+    type Params = GetQuizzesByCoursesParams;
+    type Response = Quizzes;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetQuizzesByCoursesParams {
+    pub courseids: Vec<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Quizzes {
+    pub quizzes: Vec<Quiz>,
+}
+
+/// Settings other than the name are left out for users who can't view the
+/// quiz yet, hence the defaults.
+#[derive(Debug, Deserialize)]
+pub struct Quiz {
+    pub id: u64,
+    pub course: u64,
+    pub coursemodule: u64,
+    pub name: String,
+    /// Unix timestamp, 0 when always open.
+    #[serde(default)]
+    pub timeopen: i64,
+    /// Unix timestamp, 0 when never closed.
+    #[serde(default)]
+    pub timeclose: i64,
+    /// Seconds, 0 for no limit.
+    #[serde(default)]
+    pub timelimit: u64,
+    /// 0 for unlimited.
+    #[serde(default)]
+    pub attempts: u32,
+    /// Maximum grade.
+    #[serde(default)]
+    pub grade: Option<f64>,
+    /// Sum of the question marks, which attempts are graded out of.
+    #[serde(default)]
+    pub sumgrades: Option<f64>,
+}
+
+impl Quiz {
+    /// Whether moodle sent the settings, i.e. the user can view the quiz.
+    pub fn has_settings(&self) -> bool {
+        self.grade.is_some()
+    }
+}
+
+pub struct GetUserAttempts;
+
+impl WsFunction for GetUserAttempts {
+    const NAME: &'static str = "mod_quiz_get_user_attempts";
+    type Params = GetUserAttemptsParams;
+    type Response = QuizAttempts;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetUserAttemptsParams {
+    pub quizid: u64,
+    pub userid: u64,
+    /// `all`, `finished` or `unfinished`.
+    pub status: &'static str,
+    pub includepreviews: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuizAttempts {
+    pub attempts: Vec<QuizAttempt>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuizAttempt {
+    pub id: u64,
+    /// Number of the attempt, from 1.
+    pub attempt: u32,
+    /// `inprogress`, `overdue`, `finished` or `abandoned`.
+    pub state: String,
+    pub timestart: i64,
+    /// 0 while in progress.
+    #[serde(default)]
+    pub timefinish: i64,
+    /// Out of the quiz's `sumgrades`, `None` until graded.
+    #[serde(default)]
+    pub sumgrades: Option<f64>,
+}
+
+pub struct GetUserBestGrade;
+
+impl WsFunction for GetUserBestGrade {
+    const NAME: &'static str = "mod_quiz_get_user_best_grade";
+    type Params = GetUserBestGradeParams;
+    type Response = BestGrade;
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetUserBestGradeParams {
+    pub quizid: u64,
+    pub userid: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BestGrade {
+    pub hasgrade: bool,
+    /// Out of the quiz's `grade`.
+    #[serde(default)]
+    pub grade: Option<f64>,
+    #[serde(default)]
+    pub gradetopass: Option<f64>,
+}
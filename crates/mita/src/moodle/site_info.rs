@@ -0,0 +1,18 @@
+use serde::Deserialize;
+
+use super::function::WsFunction;
+
+pub struct GetSiteInfo;
+
+impl WsFunction for GetSiteInfo {
+    const NAME: &'static str = "core_webservice_get_site_info";
+    type Params = ();
+    type Response = InfoResponse;
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct InfoResponse {
+    pub fullname: String,
+    pub userid: u64,
+    pub username: String,
+}
@@ -0,0 +1,88 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::{cache::user_digest, site_info::InfoResponse};
+
+/// When moodle last accepted or rejected each token, so that clients can be
+/// built without asking moodle on every request.
+///
+/// Tokens are told apart by the same hash as in the response cache.
+#[derive(Clone, Default)]
+pub struct Validations {
+    tokens: Arc<Mutex<HashMap<[u8; 32], Record>>>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Validation {
+    /// Moodle accepted the token, this is who it belongs to.
+    Valid(InfoResponse),
+    /// Moodle rejected the token, it has to be registered again.
+    Stale,
+}
+
+struct Record {
+    validation: Validation,
+    at: Instant,
+}
+
+impl Record {
+    fn current(&self, max_age: Duration) -> bool {
+        matches!(self.validation, Validation::Stale) || self.at.elapsed() < max_age
+    }
+}
+
+impl Validations {
+    /// The last validation of the token, unless it's older than `max_age`.
+    /// A stale token stays stale, moodle doesn't revive tokens.
+    pub fn get(&self, moodle_token: &str, max_age: Duration) -> Option<Validation> {
+        self.tokens
+            .lock()
+            .expect("moodle validations poisoned")
+            .get(&user_digest(moodle_token))
+            .filter(|record| record.current(max_age))
+            .map(|record| record.validation.clone())
+    }
+
+    /// Records a validation, forgetting those older than `max_age`.
+    pub fn record(&self, moodle_token: &str, validation: Validation, max_age: Duration) {
+        let mut tokens = self.tokens.lock().expect("moodle validations poisoned");
+        tokens.retain(|_, record| record.current(max_age));
+        tokens.insert(
+            user_digest(moodle_token),
+            Record {
+                validation,
+                at: Instant::now(),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use claims::{assert_matches, assert_none};
+
+    use super::{Validation, Validations};
+    use crate::moodle::site_info::InfoResponse;
+
+    #[test]
+    fn remembers_until_max_age() {
+        let validations = Validations::default();
+        let hour = Duration::from_secs(3600);
+        validations.record("a", Validation::Valid(InfoResponse::default()), hour);
+        validations.record("b", Validation::Stale, hour);
+
+        assert_matches!(validations.get("a", hour), Some(Validation::Valid(_)));
+        assert_matches!(validations.get("b", hour), Some(Validation::Stale));
+        assert_none!(validations.get("c", hour));
+        assert_none!(validations.get("a", Duration::ZERO));
+        assert_matches!(
+            validations.get("b", Duration::ZERO),
+            Some(Validation::Stale)
+        );
+    }
+}
@@ -0,0 +1,439 @@
+//! Channels mita pushes events to on behalf of a user, such as released
+//! grades. Only webhooks exist for now: the event is POSTed as JSON, signed
+//! with the channel's secret.
+//!
+//! The `X-Mita-Signature` header is `sha256=` followed by the hex HMAC-SHA256,
+//! keyed by the secret, of the `X-Mita-Timestamp` header, a `.` and the body.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use eyre::WrapErr;
+use hmac::{Hmac, Mac};
+use reqwest::{header::CONTENT_TYPE, redirect::Policy, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::{FromRow, SqlitePool};
+use thiserror::Error;
+use tracing::{info_span, Instrument};
+use url::{Host, Url};
+
+use crate::{clock::now, grade_watch::GradeChange};
+
+/// Longest time a webhook may take to answer.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ChannelKind {
+    Webhook,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Channel {
+    pub id: i64,
+    pub kind: ChannelKind,
+    pub target: String,
+    /// Unix timestamp.
+    pub created_at: i64,
+    /// Hex key of the signatures, only shown when the channel is added.
+    #[sqlx(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Notification {
+    GradeChanges { changes: Vec<GradeChange> },
+}
+
+#[derive(Error, Debug)]
+pub enum ChannelError {
+    #[error("channel not found")]
+    NotFound,
+    #[error("invalid channel target: {0}")]
+    InvalidTarget(String),
+    #[error("database error")]
+    Database(#[from] sqlx::Error),
+}
+
+impl ChannelError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ChannelError::NotFound => StatusCode::NOT_FOUND,
+            ChannelError::InvalidTarget(_) => StatusCode::BAD_REQUEST,
+            ChannelError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+pub async fn list(pool: &SqlitePool, subject: &str) -> Result<Vec<Channel>, ChannelError> {
+    Ok(sqlx::query_as(
+        "SELECT id, kind, target, created_at FROM notification_channels
+        WHERE subject = ? ORDER BY id",
+    )
+    .bind(subject)
+    .fetch_all(pool)
+    .await?)
+}
+
+pub async fn add(
+    pool: &SqlitePool,
+    subject: &str,
+    kind: ChannelKind,
+    target: &str,
+    now: i64,
+) -> Result<Channel, ChannelError> {
+    match kind {
+        ChannelKind::Webhook => {
+            let url = Url::parse(target).map_err(|e| ChannelError::InvalidTarget(e.to_string()))?;
+            webhook_addr(&url).await?;
+        }
+    }
+
+    let mut secret = [0; 32];
+    OsRng.fill_bytes(&mut secret);
+    let secret = hex::encode(secret);
+
+    let (id,): (i64,) = sqlx::query_as(
+        "INSERT INTO notification_channels (subject, kind, target, created_at, secret)
+        VALUES (?, ?, ?, ?, ?)
+        RETURNING id",
+    )
+    .bind(subject)
+    .bind(kind)
+    .bind(target)
+    .bind(now)
+    .bind(&secret)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Channel {
+        id,
+        kind,
+        target: target.into(),
+        created_at: now,
+        secret: Some(secret),
+    })
+}
+
+pub async fn remove(pool: &SqlitePool, subject: &str, id: i64) -> Result<(), ChannelError> {
+    let res = sqlx::query("DELETE FROM notification_channels WHERE subject = ? AND id = ?")
+        .bind(subject)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    match res.rows_affected() {
+        0 => Err(ChannelError::NotFound),
+        _ => Ok(()),
+    }
+}
+
+/// Delivers a notification to every channel of the user. A failing channel
+/// is logged and doesn't stop delivery to the others.
+#[tracing::instrument(skip(pool, notification))]
+pub async fn send(
+    pool: &SqlitePool,
+    subject: &str,
+    notification: &Notification,
+) -> Result<(), ChannelError> {
+    let channels: Vec<(i64, ChannelKind, String, String)> = sqlx::query_as(
+        "SELECT id, kind, target, secret FROM notification_channels
+        WHERE subject = ? ORDER BY id",
+    )
+    .bind(subject)
+    .fetch_all(pool)
+    .await?;
+
+    for (id, kind, target, secret) in channels {
+        let res = match kind {
+            ChannelKind::Webhook => {
+                send_webhook(&target, &secret, notification)
+                    .instrument(info_span!("sending webhook", channel = id))
+                    .await
+            }
+        };
+        if let Err(error) = res {
+            tracing::warn!(channel = id, ?error, "error sending notification");
+        }
+    }
+
+    Ok(())
+}
+
+async fn send_webhook(target: &str, secret: &str, notification: &Notification) -> eyre::Result<()> {
+    let url = Url::parse(target).wrap_err("invalid webhook url")?;
+    // the host may resolve elsewhere since the webhook was added
+    let addr = webhook_addr(&url).await?;
+    post_webhook(&url, addr, secret, notification, now()).await
+}
+
+/// Hex HMAC-SHA256 of `{timestamp}.{body}` under `secret`.
+fn signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Posts to the webhook at `addr`, which was checked, rather than wherever
+/// its host resolves to by now.
+async fn post_webhook(
+    url: &Url,
+    addr: SocketAddr,
+    secret: &str,
+    notification: &Notification,
+    timestamp: i64,
+) -> eyre::Result<()> {
+    let client = reqwest::Client::builder()
+        .resolve(url.host_str().unwrap_or_default(), addr)
+        // a redirect could lead anywhere
+        .redirect(Policy::none())
+        .connect_timeout(WEBHOOK_TIMEOUT)
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()
+        .wrap_err("error building webhook client")?;
+
+    let body = serde_json::to_vec(notification).wrap_err("error serializing notification")?;
+    client
+        .post(url.clone())
+        .header(CONTENT_TYPE, "application/json")
+        .header("X-Mita-Timestamp", timestamp)
+        .header(
+            "X-Mita-Signature",
+            format!("sha256={}", signature(secret, timestamp, &body)),
+        )
+        .body(body)
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .wrap_err("error posting to webhook")?;
+
+    Ok(())
+}
+
+/// Where a webhook is delivered to. Only https urls are accepted, and only
+/// when every address of the host is public, so that webhooks can't reach
+/// vault, cloud metadata or other services next to mita.
+async fn webhook_addr(url: &Url) -> Result<SocketAddr, ChannelError> {
+    if url.scheme() != "https" {
+        return Err(ChannelError::InvalidTarget("not an https url".into()));
+    }
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<SocketAddr> = match url.host() {
+        Some(Host::Domain(domain)) => tokio::net::lookup_host((domain, port))
+            .await
+            .map_err(|e| ChannelError::InvalidTarget(format!("cannot resolve host: {e}")))?
+            .collect(),
+        Some(Host::Ipv4(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(Host::Ipv6(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        None => vec![],
+    };
+
+    match addrs.first() {
+        Some(addr) if addrs.iter().all(|addr| is_public(addr.ip())) => Ok(*addr),
+        _ => Err(ChannelError::InvalidTarget("host is not public".into())),
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // this network, 0.0.0.0/8
+                || a == 0
+                // shared address space, 100.64.0.0/10
+                || (a == 100 && b & 0xc0 == 64)
+                // benchmarking, 198.18.0.0/15
+                || (a == 198 && b & 0xfe == 18)
+                // reserved, 240.0.0.0/4, and broadcast
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => match embedded_ipv4(ip) {
+            Some(ip) => is_public(ip.into()),
+            None => {
+                let [first, second, ..] = ip.segments();
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // unique local, fc00::/7
+                    || first & 0xfe00 == 0xfc00
+                    // link-local, fe80::/10
+                    || first & 0xffc0 == 0xfe80
+                    // local-use NAT64, 64:ff9b:1::/48
+                    || (first == 0x64 && second == 0xff9b)
+                    // teredo, 2001::/32, and documentation, 2001:db8::/32
+                    || (first == 0x2001 && (second == 0 || second == 0xdb8)))
+            }
+        },
+    }
+}
+
+/// The IPv4 address an IPv6 address leads to: IPv4-mapped, NAT64 and 6to4
+/// addresses reach the IPv4 address inside them.
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let ipv4 = |high: u16, low: u16| Ipv4Addr::from(u32::from(high) << 16 | u32::from(low));
+    match ip.segments() {
+        // IPv4-mapped, ::ffff:0:0/96
+        [0, 0, 0, 0, 0, 0xffff, high, low] => Some(ipv4(high, low)),
+        // NAT64, 64:ff9b::/96
+        [0x64, 0xff9b, 0, 0, 0, 0, high, low] => Some(ipv4(high, low)),
+        // 6to4, 2002::/16
+        [0x2002, high, low, ..] => Some(ipv4(high, low)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sqlx::SqlitePool;
+    use wiremock::{matchers, Mock, MockServer, ResponseTemplate};
+
+    use super::{
+        add, is_public, list, post_webhook, remove, send, signature, ChannelError, ChannelKind,
+        Notification,
+    };
+
+    async fn pool() -> eyre::Result<SqlitePool> {
+        let pool = SqlitePool::connect("sqlite::memory:").await?;
+        sqlx::migrate!("../../db/migrations").run(&pool).await?;
+        Ok(pool)
+    }
+
+    #[tokio::test]
+    async fn channels_belong_to_their_user() -> eyre::Result<()> {
+        let pool = pool().await?;
+        let channel = add(
+            &pool,
+            "khang",
+            ChannelKind::Webhook,
+            "https://1.1.1.1/hook",
+            1,
+        )
+        .await?;
+        assert_eq!(channel.secret.as_ref().map(String::len), Some(64));
+        assert!(list(&pool, "khang").await?[0].secret.is_none());
+
+        assert_eq!(list(&pool, "khang").await?.len(), 1);
+        assert!(list(&pool, "minh").await?.is_empty());
+        claims::assert_matches!(
+            remove(&pool, "minh", channel.id).await,
+            Err(ChannelError::NotFound)
+        );
+        remove(&pool, "khang", channel.id).await?;
+        assert!(list(&pool, "khang").await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejects_non_https_and_internal_webhooks() -> eyre::Result<()> {
+        let pool = pool().await?;
+
+        for target in [
+            "file:///etc/passwd",
+            "http://1.1.1.1/hook",
+            "https://localhost/hook",
+            "https://127.0.0.1:8200/v1/secret",
+            "https://169.254.169.254/latest/meta-data",
+            "https://10.0.0.1/hook",
+            "https://0.0.0.0/hook",
+            "https://[::1]/hook",
+            "https://[::ffff:192.168.1.1]/hook",
+            "https://[fd00::1]/hook",
+            "https://[64:ff9b::a00:1]/hook",
+            "https://[2002:c0a8:101::1]/hook",
+            "https://198.18.0.1/hook",
+            "https://240.0.0.1/hook",
+        ] {
+            let res = add(&pool, "khang", ChannelKind::Webhook, target, 1).await;
+            claims::assert_matches!(res, Err(ChannelError::InvalidTarget(_)), "{target}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn checks_ipv4_inside_ipv6() {
+        for (ip, public) in [
+            ("64:ff9b::7f00:1", false),
+            ("64:ff9b::a9fe:a9fe", false),
+            ("64:ff9b::101:101", true),
+            ("2002:a00:1::", false),
+            ("2002:101:101::", true),
+            ("::ffff:10.0.0.1", false),
+            ("2606:4700::1111", true),
+            ("2001:db8::1", false),
+            ("198.19.255.255", false),
+            ("255.255.255.255", false),
+            ("1.1.1.1", true),
+        ] {
+            assert_eq!(is_public(ip.parse().unwrap()), public, "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn skips_webhooks_that_became_internal() -> eyre::Result<()> {
+        let pool = pool().await?;
+        let mock = MockServer::start().await;
+        Mock::given(matchers::any())
+            .respond_with(ResponseTemplate::new(204))
+            .expect(0)
+            .mount(&mock)
+            .await;
+        // as if it resolved to a public address when added
+        sqlx::query(
+            "INSERT INTO notification_channels (subject, kind, target, created_at)
+            VALUES ('khang', 'webhook', ?, 1)",
+        )
+        .bind(format!("https://{}/hook", mock.address()))
+        .execute(&pool)
+        .await?;
+
+        let notification = Notification::GradeChanges { changes: vec![] };
+        send(&pool, "khang", &notification).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn posts_to_checked_address() -> eyre::Result<()> {
+        let mock = MockServer::start().await;
+        let body = br#"{"event":"grade_changes","changes":[]}"#;
+        Mock::given(matchers::method("POST"))
+            .and(matchers::body_json(json!({
+                "event": "grade_changes",
+                "changes": [],
+            })))
+            .and(matchers::header("X-Mita-Timestamp", "1700000000"))
+            .and(matchers::header(
+                "X-Mita-Signature",
+                format!("sha256={}", signature("secret", 1700000000, body)).as_str(),
+            ))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock)
+            .await;
+        let url = format!("http://hook.invalid:{}/", mock.address().port()).parse()?;
+
+        let notification = Notification::GradeChanges { changes: vec![] };
+        post_webhook(&url, *mock.address(), "secret", &notification, 1700000000).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Sliding window of recent actions per user, to cap how often each user may
+/// do something, such as sending messages.
+#[derive(Clone, Default)]
+pub struct RateLimiter(Arc<Mutex<HashMap<String, VecDeque<Instant>>>>);
+
+impl RateLimiter {
+    /// Records an action of `user` if they did less than `max` within the
+    /// last `window`, otherwise returns how long until they may act again.
+    pub fn check(&self, user: &str, max: usize, window: Duration) -> Result<(), Duration> {
+        self.check_at(user, max, window, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        user: &str,
+        max: usize,
+        window: Duration,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let mut users = self.0.lock().expect("rate limiter poisoned");
+        // forget users who have been idle for a whole window
+        users.retain(|_, actions| {
+            actions
+                .back()
+                .is_some_and(|&at| now.duration_since(at) < window)
+        });
+
+        let actions = users.entry(user.into()).or_default();
+        while actions
+            .front()
+            .is_some_and(|&at| now.duration_since(at) >= window)
+        {
+            actions.pop_front();
+        }
+        if actions.len() >= max {
+            let oldest = actions.front().copied().unwrap_or(now);
+            return Err(window.saturating_sub(now.duration_since(oldest)));
+        }
+        actions.push_back(now);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::RateLimiter;
+
+    #[test]
+    fn limits_each_user_within_window() {
+        let limiter = RateLimiter::default();
+        let window = Duration::from_secs(60);
+        let start = Instant::now();
+
+        assert!(limiter.check_at("khang", 2, window, start).is_ok());
+        assert!(limiter.check_at("khang", 2, window, start).is_ok());
+        let retry_after = limiter
+            .check_at("khang", 2, window, start + Duration::from_secs(20))
+            .unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(40));
+        assert!(limiter.check_at("minh", 2, window, start).is_ok());
+
+        assert!(limiter.check_at("khang", 2, window, start + window).is_ok());
+    }
+}
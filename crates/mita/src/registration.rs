@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// What mita knows about a user's moodle token, without the token itself.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+// secrets written before registrations were recorded only have the token
+#[serde(default)]
+pub struct Registration {
+    pub moodle_username: String,
+    /// Unix timestamp.
+    pub registered_at: i64,
+    /// Unix timestamp of the last time moodle accepted the token.
+    pub validated_at: i64,
+    /// Moodle rejected the token, the user has to register a new one.
+    pub stale: bool,
+}
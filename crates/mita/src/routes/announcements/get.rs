@@ -0,0 +1,229 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use axum::{
+    extract::Query,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    html,
+    moodle::{
+        self,
+        error::MoodleError,
+        forum::{
+            Discussion, Forum, GetForumsByCourses, GetForumsByCoursesParams, SORT_CREATED_DESC,
+        },
+    },
+    routes::forums::get::forum_discussions,
+};
+
+const MAX_LIMIT: u32 = 100;
+const DEFAULT_LIMIT: u32 = 20;
+/// News forums fetched at once.
+const CONCURRENCY: usize = 4;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AnnouncementsQuery {
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnnouncementResponse {
+    /// Discussion id, its posts are at `/discussions/{id}/posts`.
+    pub id: u64,
+    pub course_id: u64,
+    pub course_name: String,
+    pub forum_id: u64,
+    pub subject: String,
+    /// Plain text.
+    pub message: String,
+    pub author: Option<String>,
+    pub posted_at: i64,
+    pub pinned: bool,
+}
+
+/// An announcement with the course it was posted in.
+#[derive(Debug)]
+pub struct Announcement {
+    pub course_id: u64,
+    pub course_name: String,
+    pub forum_id: u64,
+    pub discussion: Discussion,
+}
+
+impl From<Announcement> for AnnouncementResponse {
+    fn from(announcement: Announcement) -> Self {
+        let discussion = announcement.discussion;
+        Self {
+            id: discussion.discussion,
+            course_id: announcement.course_id,
+            course_name: announcement.course_name,
+            forum_id: announcement.forum_id,
+            subject: discussion.subject,
+            message: html::to_text(&discussion.message),
+            author: discussion.userfullname,
+            posted_at: discussion.created,
+            pinned: discussion.pinned,
+        }
+    }
+}
+
+/// Newest first by when they were posted, however recently they were
+/// replied to.
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_announcements(
+    moodle: Extension<moodle::Client>,
+    query: Query<AnnouncementsQuery>,
+) -> Result<Json<Vec<AnnouncementResponse>>, AnnouncementsError> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let announcements = announcements(&moodle, limit).await?;
+
+    Ok(Json(
+        announcements
+            .into_iter()
+            .map(AnnouncementResponse::from)
+            .collect(),
+    ))
+}
+
+/// The latest `limit` posts of the news forums of every enrolled course,
+/// newest first.
+///
+/// Each forum is asked for its newest discussions rather than its most
+/// active, otherwise an old thread with fresh replies would push new
+/// announcements out of the page. News forums the user can't read are left
+/// out.
+pub async fn announcements(
+    moodle: &moodle::Client,
+    limit: u32,
+) -> Result<Vec<Announcement>, MoodleError> {
+    let courses = moodle.get_courses().await?;
+    if courses.is_empty() {
+        return Ok(vec![]);
+    }
+    let course_names: HashMap<_, _> = courses.iter().map(|c| (c.id, c.fullname.clone())).collect();
+
+    let forums = moodle
+        .call::<GetForumsByCourses>(&GetForumsByCoursesParams {
+            courseids: courses.iter().map(|c| c.id).collect(),
+        })
+        .await?;
+
+    let news: Vec<_> = stream::iter(forums.into_iter().filter(|f| f.kind == "news"))
+        .map(|forum| async move {
+            match forum_discussions(moodle, forum.id, SORT_CREATED_DESC, 0, limit).await {
+                Ok(discussions) => Ok(Some((forum, discussions))),
+                Err(MoodleError::Api(error)) if error.kind.is_access_restriction() => {
+                    tracing::debug!(forum = forum.id, ?error, "cannot read news forum");
+                    Ok(None)
+                }
+                Err(e) => Err(e),
+            }
+        })
+        .buffer_unordered(CONCURRENCY)
+        .try_filter_map(|news| async move { Ok(news) })
+        .try_collect()
+        .await?;
+
+    Ok(latest(news, &course_names, limit))
+}
+
+/// The newest `limit` discussions of `news`, with the names of their courses.
+fn latest(
+    news: Vec<(Forum, Vec<Discussion>)>,
+    course_names: &HashMap<u64, String>,
+    limit: u32,
+) -> Vec<Announcement> {
+    let mut announcements: Vec<_> = news
+        .into_iter()
+        .flat_map(|(forum, discussions)| {
+            let course_name = course_names.get(&forum.course).cloned().unwrap_or_default();
+            discussions.into_iter().map(move |discussion| Announcement {
+                course_id: forum.course,
+                course_name: course_name.clone(),
+                forum_id: forum.id,
+                discussion,
+            })
+        })
+        .collect();
+    announcements.sort_by_key(|a| Reverse(a.discussion.created));
+    announcements.truncate(limit as usize);
+    announcements
+}
+
+#[derive(Error, Debug)]
+pub enum AnnouncementsError {
+    #[error("error from moodle")]
+    Moodle(#[from] MoodleError),
+}
+
+impl IntoResponse for AnnouncementsError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            AnnouncementsError::Moodle(e) => e.status(),
+        };
+        tracing::error!(service = "moodle", %status, error = ?self);
+        status.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn forum(id: u64, course: u64) -> Forum {
+        serde_json::from_value(json!({
+            "id": id,
+            "course": course,
+            "type": "news",
+            "name": "Announcements",
+            "cmid": id + 100,
+        }))
+        .unwrap()
+    }
+
+    fn discussion(id: u64, created: i64) -> Discussion {
+        serde_json::from_value(json!({
+            "id": id,
+            "discussion": id,
+            "name": format!("discussion {id}"),
+            "subject": format!("discussion {id}"),
+            "message": "<p>Hi</p>",
+            "userid": 2,
+            "created": created,
+            "timemodified": created,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn newest_across_forums() {
+        let names = HashMap::from([(1, "Algo".to_string())]);
+        let announcements = latest(
+            vec![
+                (forum(10, 1), vec![discussion(1, 300), discussion(2, 100)]),
+                (forum(20, 2), vec![discussion(3, 200), discussion(4, 50)]),
+            ],
+            &names,
+            3,
+        );
+
+        let ids = announcements
+            .iter()
+            .map(|a| a.discussion.discussion)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [1, 3, 2]);
+        assert_eq!(announcements[0].course_name, "Algo");
+        assert_eq!(announcements[0].forum_id, 10);
+        // courses missing from the names get an empty one
+        assert_eq!(announcements[1].course_id, 2);
+        assert_eq!(announcements[1].course_name, "");
+    }
+}
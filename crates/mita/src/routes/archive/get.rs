@@ -0,0 +1,462 @@
+use std::{collections::HashSet, time::Duration};
+
+use axum::{
+    body::{Bytes, StreamBody},
+    extract::{Path, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use futures::StreamExt;
+use reqwest::StatusCode;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+use crate::{
+    app_state::AppState,
+    clock::now,
+    moodle::{
+        self,
+        contents::{GetContents, GetContentsParams, Section},
+        error::MoodleError,
+    },
+    oidc::Claims,
+    user_slots::Slot,
+    zip::ZipWriter,
+};
+
+/// Name of the manifest inside the archive. Its tags can be sent back in
+/// `If-None-Match` to leave out files the client already has.
+const MANIFEST: &str = "mita-manifest.json";
+
+/// Longest file or folder name in the archive, in bytes.
+const MAX_NAME: usize = 120;
+
+/// Longest wait for moodle to start or go on sending a file.
+const STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+struct ArchiveFile {
+    path: String,
+    url: String,
+    size: u64,
+    modified: i64,
+    tag: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Manifest {
+    course_id: u64,
+    files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestEntry {
+    path: String,
+    tag: String,
+    status: EntryStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum EntryStatus {
+    Included,
+    /// Listed in `If-None-Match`.
+    Unchanged,
+    /// Moodle refused to hand out the file.
+    Failed,
+}
+
+/// Every file of a course as a ZIP laid out as `section/module/filename`,
+/// streamed while the files are downloaded from moodle.
+#[axum::debug_handler(state = AppState)]
+#[tracing::instrument(skip(claims, moodle, state, headers))]
+pub async fn get_course_archive(
+    claims: Extension<Claims>,
+    moodle: Extension<moodle::Client>,
+    state: State<AppState>,
+    Path(course_id): Path<u64>,
+    headers: HeaderMap,
+) -> Result<Response, ArchiveError> {
+    let config = &state.config.archive;
+    let slot = state
+        .archive_slots
+        .try_acquire(&claims.sub, config.max_archives)
+        .ok_or(ArchiveError::TooManyArchives)?;
+
+    let sections = moodle
+        .call::<GetContents>(&GetContentsParams {
+            courseid: course_id,
+        })
+        .await?;
+
+    let known = headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(parse_tags)
+        .collect::<HashSet<_>>();
+
+    let (files, unchanged): (Vec<_>, Vec<_>) = archive_files(sections)
+        .into_iter()
+        .partition(|file| !known.contains(&file.tag));
+
+    if files.is_empty() && !unchanged.is_empty() {
+        return Ok(StatusCode::NOT_MODIFIED.into_response());
+    }
+
+    let size: u64 = files.iter().map(|f| f.size).sum();
+    if size > config.max_bytes {
+        return Err(ArchiveError::TooLarge(size));
+    }
+
+    let manifest = Manifest {
+        course_id,
+        files: unchanged
+            .into_iter()
+            .map(|file| ManifestEntry {
+                path: file.path,
+                tag: file.tag,
+                status: EntryStatus::Unchanged,
+            })
+            .collect(),
+    };
+
+    let (tx, mut rx) = mpsc::channel(config.concurrency.max(1));
+    let closed = tx.clone();
+    let archive = write_archive(
+        moodle.0,
+        files,
+        manifest,
+        config.concurrency,
+        config.max_bytes,
+        slot,
+        tx,
+    );
+    tokio::spawn(async move {
+        tokio::select! {
+            () = archive => {}
+            // frees the slot even while waiting on moodle
+            () = closed.closed() => tracing::debug!("client went away, archive stopped"),
+        }
+    });
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"course-{course_id}.zip\""),
+            ),
+        ],
+        StreamBody::new(futures::stream::poll_fn(move |cx| rx.poll_recv(cx))),
+    )
+        .into_response())
+}
+
+type Chunks = mpsc::Sender<Result<Bytes, std::io::Error>>;
+
+/// Downloads up to `concurrency` files ahead while writing them into the
+/// archive in order. Gives up on files moodle stops sending for
+/// [`STALL_TIMEOUT`].
+async fn write_archive(
+    moodle: moodle::Client,
+    files: Vec<ArchiveFile>,
+    mut manifest: Manifest,
+    concurrency: usize,
+    max_bytes: u64,
+    // held until the archive is done
+    _slot: Slot,
+    mut tx: Chunks,
+) {
+    let mut zip = ZipWriter::default();
+    let mut written = 0;
+
+    let mut downloads = futures::stream::iter(files)
+        .map(|file| {
+            let moodle = moodle.clone();
+            async move {
+                let res = match moodle.pluginfile_url(&file.url) {
+                    Some(url) => tokio::time::timeout(STALL_TIMEOUT, moodle.download(&url, None))
+                        .await
+                        .map_err(|_| eyre::eyre!("moodle stalled"))
+                        .and_then(|res| Ok(res?))
+                        .and_then(|res| Ok(res.error_for_status()?)),
+                    None => Err(eyre::eyre!("file url not from moodle")),
+                };
+                (file, res)
+            }
+        })
+        .buffered(concurrency.max(1));
+
+    while let Some((file, res)) = downloads.next().await {
+        let status = match res {
+            Ok(res) => {
+                let res = write_entry(&mut zip, &mut tx, &file, res, &mut written, max_bytes).await;
+                if let Err(error) = res {
+                    tracing::warn!(?error, path = %file.path, "aborting archive");
+                    let _ = tx.send(Err(std::io::Error::other(error))).await;
+                    return;
+                }
+                EntryStatus::Included
+            }
+            Err(error) => {
+                tracing::warn!(?error, path = %file.path, "error downloading file for archive");
+                EntryStatus::Failed
+            }
+        };
+        manifest.files.push(ManifestEntry {
+            path: file.path,
+            tag: file.tag,
+            status,
+        });
+    }
+
+    let res = async {
+        let manifest = serde_json::to_vec_pretty(&manifest)?;
+        send(&mut tx, zip.start_file(MANIFEST, now())?).await?;
+        zip.write(&manifest)?;
+        send(&mut tx, manifest).await?;
+        send(&mut tx, zip.finish_file()).await?;
+        send(&mut tx, zip.finish()?).await
+    }
+    .await;
+    if let Err(error) = res {
+        tracing::warn!(?error, "error finishing archive");
+        let _ = tx.send(Err(std::io::Error::other(error))).await;
+    }
+}
+
+async fn write_entry(
+    zip: &mut ZipWriter,
+    tx: &mut Chunks,
+    file: &ArchiveFile,
+    res: reqwest::Response,
+    written: &mut u64,
+    max_bytes: u64,
+) -> eyre::Result<()> {
+    send(tx, zip.start_file(&file.path, file.modified)?).await?;
+
+    let mut body = res.bytes_stream();
+    while let Some(chunk) = tokio::time::timeout(STALL_TIMEOUT, body.next())
+        .await
+        .map_err(|_| eyre::eyre!("moodle stalled"))?
+    {
+        let chunk = chunk?;
+        // moodle's reported sizes were checked already, but can't be trusted
+        *written += chunk.len() as u64;
+        if *written > max_bytes {
+            eyre::bail!("archive grew past {max_bytes} bytes");
+        }
+        zip.write(&chunk)?;
+        send(tx, chunk).await?;
+    }
+
+    send(tx, zip.finish_file()).await
+}
+
+async fn send(tx: &mut Chunks, chunk: impl Into<Bytes>) -> eyre::Result<()> {
+    tx.send(Ok(chunk.into()))
+        .await
+        .map_err(|_| eyre::eyre!("client went away"))
+}
+
+/// Every file of the course with a unique path in the archive.
+fn archive_files(sections: Vec<Section>) -> Vec<ArchiveFile> {
+    let mut paths = HashSet::new();
+    let mut files = vec![];
+
+    for section in sections {
+        let section_name = match section.name.trim() {
+            "" => format!("Section {}", section.section),
+            name => name.to_string(),
+        };
+        for module in section.modules {
+            for content in module.contents {
+                let Some(url) = content.fileurl.filter(|_| content.kind == "file") else {
+                    continue;
+                };
+
+                let mut path = vec![sanitize(&section_name), sanitize(&module.name)];
+                path.extend(
+                    content
+                        .filepath
+                        .as_deref()
+                        .unwrap_or("/")
+                        .split('/')
+                        .filter(|c| !c.is_empty() && *c != "." && *c != "..")
+                        .map(sanitize),
+                );
+                path.push(sanitize(&content.filename));
+                let path = unique(&mut paths, path.join("/"));
+
+                files.push(ArchiveFile {
+                    tag: tag(&url, content.timemodified, content.filesize),
+                    path,
+                    url,
+                    size: content.filesize,
+                    modified: content.timemodified,
+                });
+            }
+        }
+    }
+
+    files
+}
+
+/// Identifies a version of a file, stable across archives.
+fn tag(url: &str, modified: i64, size: u64) -> String {
+    let hash = Sha256::new()
+        .chain_update(url)
+        .chain_update(modified.to_le_bytes())
+        .chain_update(size.to_le_bytes())
+        .finalize();
+    hex::encode(&hash[..12])
+}
+
+/// Reads an `If-None-Match` style list: `"a", W/"b", c`.
+fn parse_tags(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/").trim_matches('"'))
+        .filter(|tag| !tag.is_empty())
+        .map(String::from)
+}
+
+/// Makes a name safe as a single path component on every OS.
+fn sanitize(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let mut name = name.trim().trim_matches('.').to_string();
+
+    if name.len() > MAX_NAME {
+        let mut end = MAX_NAME;
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        name.truncate(end);
+    }
+
+    match name.is_empty() {
+        true => "_".into(),
+        false => name,
+    }
+}
+
+/// Appends ` (2)`, ` (3)`, ... before the extension of a taken path.
+fn unique(paths: &mut HashSet<String>, path: String) -> String {
+    if paths.insert(path.to_lowercase()) {
+        return path;
+    }
+
+    let (stem, extension) = match path.rsplit_once('.') {
+        Some((stem, extension)) if !stem.ends_with('/') && !extension.contains('/') => {
+            (stem, format!(".{extension}"))
+        }
+        _ => (path.as_str(), String::new()),
+    };
+    (2..)
+        .map(|n| format!("{stem} ({n}){extension}"))
+        .find(|candidate| paths.insert(candidate.to_lowercase()))
+        .expect("some suffix is free")
+}
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("error getting course contents from moodle")]
+    Moodle(#[from] MoodleError),
+    #[error("too many archives being built for the user")]
+    TooManyArchives,
+    #[error("archive of {0} bytes is too large")]
+    TooLarge(u64),
+}
+
+impl IntoResponse for ArchiveError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            ArchiveError::Moodle(e) => e.status(),
+            ArchiveError::TooManyArchives => StatusCode::TOO_MANY_REQUESTS,
+            ArchiveError::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+        };
+        let service = match &self {
+            ArchiveError::Moodle(_) => "moodle",
+            _ => "mita",
+        };
+        tracing::error!(%service, %status, error = ?self);
+        status.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{archive_files, parse_tags, sanitize};
+    use crate::moodle::contents::Section;
+
+    #[test]
+    fn lays_out_section_module_filename() -> eyre::Result<()> {
+        let file = |name: &str, path: &str| {
+            json!({
+                "type": "file",
+                "filename": name,
+                "filepath": path,
+                "filesize": 10,
+                "fileurl": format!("https://e-learning.hcmut.edu.vn/webservice/pluginfile.php/1{path}{name}"),
+                "timemodified": 1678000000,
+            })
+        };
+        let sections: Vec<Section> = serde_json::from_value(json!([{
+            "id": 1,
+            "name": "",
+            "section": 0,
+            "modules": [
+                {
+                    "id": 10, "name": "Slides: week 1", "instance": 1, "modname": "resource",
+                    "contents": [file("a.pdf", "/"), file("A.pdf", "/")],
+                },
+                {
+                    "id": 11, "name": "Labs", "instance": 2, "modname": "folder",
+                    "contents": [file("lab.c", "/../src/"), { "type": "url", "fileurl": "https://example.com" }],
+                },
+            ],
+        }]))?;
+
+        let paths: Vec<_> = archive_files(sections)
+            .into_iter()
+            .map(|f| f.path)
+            .collect();
+
+        assert_eq!(
+            paths,
+            [
+                "Section 0/Slides_ week 1/a.pdf",
+                "Section 0/Slides_ week 1/A (2).pdf",
+                "Section 0/Labs/src/lab.c",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sanitizes_names() {
+        assert_eq!(sanitize("a/b\\c"), "a_b_c");
+        assert_eq!(sanitize(".."), "_");
+        assert_eq!(sanitize("đề thi.pdf"), "đề thi.pdf");
+        assert!(sanitize(&"ạ".repeat(100)).len() <= 120);
+    }
+
+    #[test]
+    fn parses_if_none_match() {
+        let tags: Vec<_> = parse_tags(r#""a", W/"b",c , "#).collect();
+        assert_eq!(tags, ["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,205 @@
+use axum::{
+    extract::Path,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use reqwest::StatusCode;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    html,
+    moodle::{
+        self,
+        assign::{
+            AssignIdParams, GetAssignments, GetAssignmentsParams, GetSubmissionStatus,
+            SubmissionFile, SubmissionPlugin,
+        },
+        course::{CourseModule, GetCourseModule, GetCourseModuleParams},
+        error::MoodleError,
+    },
+};
+
+#[derive(Debug, Serialize)]
+pub struct AssignmentStatus {
+    pub cmid: u64,
+    pub assignment_id: u64,
+    pub course_id: u64,
+    pub name: String,
+    pub due: Option<i64>,
+    pub cutoff: Option<i64>,
+    /// Due date granted to the user, if any.
+    pub extension_due: Option<i64>,
+    /// `new`, `draft`, `submitted` or `reopened`, `None` before the first
+    /// attempt.
+    pub submission_status: Option<String>,
+    pub grading_status: Option<String>,
+    pub locked: bool,
+    pub can_edit: bool,
+    pub can_submit: bool,
+    pub last_modified: Option<i64>,
+    pub files: Vec<SubmittedFile>,
+    /// Plain text.
+    pub online_text: Option<String>,
+    pub grade: Option<String>,
+    pub graded_at: Option<i64>,
+    /// Plain text.
+    pub feedback: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubmittedFile {
+    pub filename: String,
+    pub size: u64,
+    pub mime_type: Option<String>,
+    /// Moodle pluginfile url, download it through `/files`.
+    pub url: Option<String>,
+    pub modified_at: Option<i64>,
+}
+
+impl From<SubmissionFile> for SubmittedFile {
+    fn from(file: SubmissionFile) -> Self {
+        Self {
+            filename: file.filename,
+            size: file.filesize,
+            mime_type: file.mimetype,
+            url: file.fileurl,
+            modified_at: (file.timemodified != 0).then_some(file.timemodified),
+        }
+    }
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_assignment_status(
+    moodle: Extension<moodle::Client>,
+    Path(cmid): Path<u64>,
+) -> Result<Json<AssignmentStatus>, AssignmentError> {
+    let cm = assignment_module(&moodle, cmid).await?;
+
+    Ok(Json(assignment_status(&moodle, &cm).await?))
+}
+
+/// The course module `cmid`, if it is an assignment.
+pub async fn assignment_module(
+    moodle: &moodle::Client,
+    cmid: u64,
+) -> Result<CourseModule, AssignmentError> {
+    let res = moodle
+        .call::<GetCourseModule>(&GetCourseModuleParams { cmid })
+        .await?;
+
+    match res.cm.modname.as_str() {
+        "assign" => Ok(res.cm),
+        _ => Err(AssignmentError::NotAnAssignment),
+    }
+}
+
+pub async fn assignment_status(
+    moodle: &moodle::Client,
+    cm: &CourseModule,
+) -> Result<AssignmentStatus, MoodleError> {
+    let status_params = AssignIdParams {
+        assignid: cm.instance,
+    };
+    let assignments_params = GetAssignmentsParams {
+        courseids: vec![cm.course],
+    };
+    let (status, assignments) = futures::try_join!(
+        moodle.call::<GetSubmissionStatus>(&status_params),
+        moodle.call::<GetAssignments>(&assignments_params),
+    )?;
+
+    let assignment = assignments
+        .courses
+        .into_iter()
+        .flat_map(|c| c.assignments)
+        .find(|a| a.id == cm.instance);
+    let attempt = status.lastattempt;
+    let submission = attempt
+        .as_ref()
+        .and_then(|a| a.teamsubmission.as_ref().or(a.submission.as_ref()));
+    let plugins = submission.map_or(&[][..], |s| &s.plugins[..]);
+
+    Ok(AssignmentStatus {
+        cmid: cm.id,
+        assignment_id: cm.instance,
+        course_id: cm.course,
+        name: cm.name.clone(),
+        due: assignment.as_ref().map(|a| a.duedate).filter(|&t| t != 0),
+        cutoff: assignment
+            .as_ref()
+            .map(|a| a.cutoffdate)
+            .filter(|&t| t != 0),
+        extension_due: attempt
+            .as_ref()
+            .and_then(|a| a.extensionduedate)
+            .filter(|&t| t != 0),
+        submission_status: submission.map(|s| s.status.clone()),
+        grading_status: attempt.as_ref().and_then(|a| a.gradingstatus.clone()),
+        locked: attempt.as_ref().is_some_and(|a| a.locked),
+        can_edit: attempt.as_ref().is_some_and(|a| a.canedit),
+        can_submit: attempt.as_ref().is_some_and(|a| a.cansubmit),
+        last_modified: submission.map(|s| s.timemodified).filter(|&t| t != 0),
+        files: plugins
+            .iter()
+            .filter(|p| p.kind == "file")
+            .flat_map(|p| &p.fileareas)
+            .flat_map(|area| &area.files)
+            .cloned()
+            .map(SubmittedFile::from)
+            .collect(),
+        online_text: editor_text(plugins, "onlinetext"),
+        grade: status
+            .feedback
+            .as_ref()
+            .and_then(|f| f.gradefordisplay.as_deref())
+            .map(html::to_text)
+            .filter(|g| !g.is_empty()),
+        graded_at: status
+            .feedback
+            .as_ref()
+            .map(|f| f.gradeddate)
+            .filter(|&t| t != 0),
+        feedback: status
+            .feedback
+            .as_ref()
+            .and_then(|f| editor_text(&f.plugins, "comments")),
+    })
+}
+
+/// Text of the first editor field of plugin `kind`.
+fn editor_text(plugins: &[SubmissionPlugin], kind: &str) -> Option<String> {
+    plugins
+        .iter()
+        .filter(|p| p.kind == kind)
+        .flat_map(|p| &p.editorfields)
+        .map(|field| html::to_text(&field.text))
+        .find(|text| !text.is_empty())
+}
+
+#[derive(Error, Debug)]
+pub enum AssignmentError {
+    #[error("course module is not an assignment")]
+    NotAnAssignment,
+    #[error("invalid submission: {0}")]
+    InvalidSubmission(String),
+    #[error("error from moodle")]
+    Moodle(#[from] MoodleError),
+}
+
+impl IntoResponse for AssignmentError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            AssignmentError::NotAnAssignment => StatusCode::NOT_FOUND,
+            AssignmentError::InvalidSubmission(_) => StatusCode::BAD_REQUEST,
+            AssignmentError::Moodle(e) => e.status(),
+        };
+        let service = match &self {
+            AssignmentError::Moodle(_) => "moodle",
+            _ => "mita",
+        };
+        tracing::error!(%service, %status, error = ?self);
+        status.into_response()
+    }
+}
@@ -0,0 +1,174 @@
+use axum::{body::Bytes, extract::Path, Extension, Json};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::Deserialize;
+
+use super::get::{assignment_module, assignment_status, AssignmentError, AssignmentStatus};
+use crate::moodle::{
+    self,
+    assign::{
+        OnlineText, PluginData, SaveSubmission, SaveSubmissionParams, SubmitForGrading,
+        SubmitForGradingParams,
+    },
+    error::{MoodleError, MoodleWarning},
+    files::UploadFile,
+};
+
+/// Request bodies carry files base64 encoded, a third larger than the files.
+pub const MAX_SUBMISSION_BODY: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub struct SubmissionData {
+    #[serde(default)]
+    pub files: Vec<SubmissionFileData>,
+    /// HTML.
+    pub online_text: Option<String>,
+    /// Submit for grading after saving, instead of leaving a draft.
+    #[serde(default = "default_submit")]
+    pub submit: bool,
+    /// The student accepts the assignment's submission statement, usually
+    /// about academic integrity. Moodle refuses to submit without it when
+    /// the assignment has one.
+    #[serde(default)]
+    pub accept_submission_statement: bool,
+}
+
+#[derive(Deserialize)]
+pub struct SubmissionFileData {
+    pub filename: String,
+    /// Base64.
+    pub content: String,
+}
+
+impl std::fmt::Debug for SubmissionFileData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubmissionFileData")
+            .field("filename", &self.filename)
+            .field("content_len", &self.content.len())
+            .finish()
+    }
+}
+
+fn default_submit() -> bool {
+    true
+}
+
+impl SubmissionData {
+    fn decode_files(&self) -> Result<Vec<UploadFile>, AssignmentError> {
+        self.files
+            .iter()
+            .map(|file| {
+                if file.filename.trim().is_empty() || file.filename.contains(['/', '\\']) {
+                    return Err(AssignmentError::InvalidSubmission(format!(
+                        "invalid filename {:?}",
+                        file.filename
+                    )));
+                }
+                let content = BASE64.decode(&file.content).map_err(|_| {
+                    AssignmentError::InvalidSubmission(format!(
+                        "content of {} is not base64",
+                        file.filename
+                    ))
+                })?;
+                Ok(UploadFile {
+                    filename: file.filename.clone(),
+                    content: Bytes::from(content),
+                })
+            })
+            .collect()
+    }
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn submit_assignment(
+    moodle: Extension<moodle::Client>,
+    Path(cmid): Path<u64>,
+    Json(data): Json<SubmissionData>,
+) -> Result<Json<AssignmentStatus>, AssignmentError> {
+    if data.files.is_empty() && data.online_text.is_none() {
+        return Err(AssignmentError::InvalidSubmission(
+            "nothing to submit".into(),
+        ));
+    }
+    let files = data.decode_files()?;
+    let cm = assignment_module(&moodle, cmid).await?;
+
+    let draft = match files.is_empty() {
+        true => None,
+        false => Some(moodle.upload(&files).await?),
+    };
+    let warnings = moodle
+        .call::<SaveSubmission>(&SaveSubmissionParams {
+            assignmentid: cm.instance,
+            plugindata: PluginData {
+                onlinetext_editor: data.online_text.map(|text| OnlineText {
+                    text,
+                    format: 1,
+                    itemid: 0,
+                }),
+                files_filemanager: draft,
+            },
+        })
+        .await?;
+    moodle.invalidate_cache();
+    MoodleWarning::check(warnings).map_err(MoodleError::from)?;
+
+    let status = assignment_status(&moodle, &cm).await?;
+    // assignments without a submit button are submitted when saved
+    if !data.submit || status.submission_status.as_deref() == Some("submitted") {
+        return Ok(Json(status));
+    }
+
+    let warnings = moodle
+        .call::<SubmitForGrading>(&SubmitForGradingParams {
+            assignmentid: cm.instance,
+            acceptsubmissionstatement: data.accept_submission_statement,
+        })
+        .await?;
+    moodle.invalidate_cache();
+    MoodleWarning::check(warnings).map_err(MoodleError::from)?;
+
+    Ok(Json(assignment_status(&moodle, &cm).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use super::*;
+
+    fn data(filename: &str, content: &str) -> SubmissionData {
+        SubmissionData {
+            files: vec![SubmissionFileData {
+                filename: filename.into(),
+                content: content.into(),
+            }],
+            online_text: None,
+            submit: true,
+            accept_submission_statement: false,
+        }
+    }
+
+    #[test]
+    fn decodes_base64_files() {
+        let files = assert_ok!(data("report.pdf", "aGVsbG8=").decode_files());
+        assert_eq!(files[0].filename, "report.pdf");
+        assert_eq!(&files[0].content[..], b"hello");
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        assert_err!(data("report.pdf", "not base64!").decode_files());
+        assert_err!(data("../report.pdf", "aGVsbG8=").decode_files());
+        assert_err!(data(" ", "aGVsbG8=").decode_files());
+    }
+
+    #[test]
+    fn submits_by_default() {
+        let data: SubmissionData = serde_json::from_str(r#"{"online_text": "hi"}"#).unwrap();
+        assert!(data.submit);
+        assert!(data.files.is_empty());
+        // only the student can accept the statement
+        assert!(!data.accept_submission_statement);
+    }
+}
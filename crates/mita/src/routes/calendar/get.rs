@@ -0,0 +1,116 @@
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::{
+    app_state::AppState,
+    clock::now,
+    feed_token::{self, FeedKind, FeedToken, FeedTokenError},
+    ics,
+    moodle::{self, error::MoodleError},
+    routes::deadlines::get::{upcoming_deadlines, DeadlinesQuery, SubmissionStatus},
+};
+
+/// How far back the feed goes, so recently passed deadlines stay visible.
+const LOOKBACK_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// Deadlines in the feed, far more than a semester has, paged from moodle.
+const FEED_LIMIT: u32 = 500;
+
+/// Reminders for deadlines that still need a submission: a day and an hour
+/// before.
+const ALARMS: [u32; 2] = [24 * 60, 60];
+
+#[axum::debug_handler(state = AppState)]
+#[tracing::instrument(skip(state, file))]
+pub async fn get_calendar_feed(
+    state: State<AppState>,
+    Path(file): Path<String>,
+) -> Result<Response, CalendarFeedError> {
+    let feed_token = file
+        .strip_suffix(".ics")
+        .and_then(|token| token.parse::<FeedToken>().ok())
+        .ok_or(FeedTokenError::NotFound)?;
+
+    let moodle_token = feed_token::resolve(&state.pool, FeedKind::Calendar, &feed_token).await?;
+    let moodle = moodle::Client::new(
+        &state.http_client,
+        &state.config.moodle,
+        moodle_token,
+        state.moodle_cache.clone(),
+        state.moodle_validations.clone(),
+        // the feed's copy may be older than the registered token
+        None,
+    )
+    .await?;
+
+    let now = now();
+    let deadlines = upcoming_deadlines(
+        &moodle,
+        &DeadlinesQuery {
+            from: Some(now - LOOKBACK_SECS),
+            to: None,
+            limit: Some(FEED_LIMIT),
+        },
+    )
+    .await?;
+
+    let events = deadlines
+        .into_iter()
+        .map(|deadline| {
+            let pending = deadline.submission_status != SubmissionStatus::Submitted;
+            ics::Event {
+                uid: format!("{}@mita", deadline.id),
+                summary: format!("[{}] {}", deadline.course_name, deadline.name),
+                description: Some(format!(
+                    "Status: {}",
+                    match deadline.submission_status {
+                        SubmissionStatus::Pending => "pending",
+                        SubmissionStatus::Overdue => "overdue",
+                        SubmissionStatus::Closed => "closed",
+                        SubmissionStatus::Submitted => "submitted",
+                    }
+                )),
+                url: deadline.url,
+                start: deadline.due,
+                alarms: if pending { ALARMS.to_vec() } else { vec![] },
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        ics::render("Moodle deadlines", &events, now),
+    )
+        .into_response())
+}
+
+#[derive(Error, Debug)]
+pub enum CalendarFeedError {
+    #[error("error resolving feed token")]
+    FeedToken(#[from] FeedTokenError),
+    #[error("error getting deadlines from moodle")]
+    Moodle(#[from] MoodleError),
+}
+
+impl IntoResponse for CalendarFeedError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            CalendarFeedError::FeedToken(e) => e.status(),
+            CalendarFeedError::Moodle(e) => e.status(),
+        };
+        let service = match &self {
+            CalendarFeedError::FeedToken(_) => "mita",
+            CalendarFeedError::Moodle(_) => "moodle",
+        };
+        match status {
+            StatusCode::NOT_FOUND => tracing::info!(%service, %status, error = ?self),
+            _ => tracing::error!(%service, %status, error = ?self),
+        }
+        status.into_response()
+    }
+}
@@ -0,0 +1,55 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use reqwest::StatusCode;
+use secrecy::ExposeSecret;
+use serde_json::json;
+use thiserror::Error;
+
+use crate::{
+    app_state::AppState,
+    clock::now,
+    feed_token::{self, FeedKind, FeedTokenError},
+    moodle,
+    oidc::Claims,
+};
+
+/// Creates a calendar feed url for the user, revoking the previous one.
+#[axum::debug_handler(state = AppState)]
+#[tracing::instrument(skip(claims, moodle, state))]
+pub async fn create_calendar_feed(
+    claims: Extension<Claims>,
+    moodle: Extension<moodle::Client>,
+    state: State<AppState>,
+) -> Result<Response, CreateFeedError> {
+    let feed_token = feed_token::create(
+        &state.pool,
+        &claims.sub,
+        FeedKind::Calendar,
+        moodle.token(),
+        now(),
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "path": format!("/calendar/{}.ics", feed_token.expose_secret()),
+        })),
+    )
+        .into_response())
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct CreateFeedError(#[from] FeedTokenError);
+
+impl IntoResponse for CreateFeedError {
+    fn into_response(self) -> Response {
+        let status = self.0.status();
+        tracing::error!(service = "mita", %status, error = ?self, "error creating calendar feed");
+        status.into_response()
+    }
+}
@@ -0,0 +1,37 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::{
+    app_state::AppState,
+    notify::{self, ChannelError},
+    oidc::Claims,
+};
+
+#[axum::debug_handler(state = AppState)]
+#[tracing::instrument(skip(claims, state))]
+pub async fn remove_channel(
+    claims: Extension<Claims>,
+    state: State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, RemoveChannelError> {
+    notify::remove(&state.pool, &claims.sub, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct RemoveChannelError(#[from] ChannelError);
+
+impl IntoResponse for RemoveChannelError {
+    fn into_response(self) -> Response {
+        let status = self.0.status();
+        tracing::error!(service = "mita", %status, error = ?self, "error removing channel");
+        status.into_response()
+    }
+}
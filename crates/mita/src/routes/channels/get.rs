@@ -0,0 +1,33 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use thiserror::Error;
+
+use crate::{
+    app_state::AppState,
+    notify::{self, Channel, ChannelError},
+    oidc::Claims,
+};
+
+#[axum::debug_handler(state = AppState)]
+#[tracing::instrument(skip(claims, state))]
+pub async fn get_channels(
+    claims: Extension<Claims>,
+    state: State<AppState>,
+) -> Result<Json<Vec<Channel>>, ListChannelsError> {
+    Ok(Json(notify::list(&state.pool, &claims.sub).await?))
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct ListChannelsError(#[from] ChannelError);
+
+impl IntoResponse for ListChannelsError {
+    fn into_response(self) -> Response {
+        let status = self.0.status();
+        tracing::error!(service = "mita", %status, error = ?self, "error listing channels");
+        status.into_response()
+    }
+}
@@ -0,0 +1,48 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    app_state::AppState,
+    clock::now,
+    notify::{self, ChannelError, ChannelKind},
+    oidc::Claims,
+};
+
+#[derive(Deserialize)]
+pub struct ChannelData {
+    kind: ChannelKind,
+    /// Https url of the webhook, on a public address.
+    target: String,
+}
+
+/// The response holds the channel's `secret`, which webhook payloads are
+/// signed with. It isn't shown again.
+#[axum::debug_handler(state = AppState)]
+#[tracing::instrument(skip(claims, state, data))]
+pub async fn add_channel(
+    claims: Extension<Claims>,
+    state: State<AppState>,
+    Json(data): Json<ChannelData>,
+) -> Result<Response, AddChannelError> {
+    let channel = notify::add(&state.pool, &claims.sub, data.kind, &data.target, now()).await?;
+
+    Ok((StatusCode::CREATED, Json(channel)).into_response())
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct AddChannelError(#[from] ChannelError);
+
+impl IntoResponse for AddChannelError {
+    fn into_response(self) -> Response {
+        let status = self.0.status();
+        tracing::error!(service = "mita", %status, error = ?self, "error adding channel");
+        status.into_response()
+    }
+}
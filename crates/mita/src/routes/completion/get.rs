@@ -0,0 +1,209 @@
+use axum::{
+    extract::Path,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    moodle::{
+        self,
+        completion::{
+            ActivityCompletionStatus, CompletionStatusParams, CourseCompletionStatus,
+            GetActivitiesCompletionStatus, GetCourseCompletionStatus,
+        },
+        error::{MoodleApiErrorKind, MoodleError},
+    },
+    routes::contents::get::{CompletionState, CompletionTracking},
+};
+
+#[derive(Debug, Serialize)]
+pub struct CourseCompletionResponse {
+    pub course_id: u64,
+    /// Activities with completion tracking.
+    pub activities: Vec<ActivityCompletionResponse>,
+    pub completed: u32,
+    pub total: u32,
+    /// Completed activities out of all tracked ones, from 0 to 100. `None`
+    /// when the course tracks no activity.
+    pub percentage: Option<f64>,
+    /// `None` when the course has no completion criteria.
+    pub course: Option<CourseCriteriaResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivityCompletionResponse {
+    pub cmid: u64,
+    /// `assign`, `quiz`, `resource`, ...
+    pub module_type: String,
+    pub instance: u64,
+    pub tracking: CompletionTracking,
+    pub state: CompletionState,
+    pub completed_at: Option<i64>,
+    /// Whether a teacher set the state.
+    pub overridden: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CourseCriteriaResponse {
+    pub completed: bool,
+    /// Whether every criterion is needed, rather than any one.
+    pub requires_all: bool,
+    pub criteria: Vec<CriterionResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CriterionResponse {
+    pub title: String,
+    /// As shown on moodle.
+    pub status: String,
+    pub complete: bool,
+    pub completed_at: Option<i64>,
+}
+
+impl ActivityCompletionResponse {
+    fn new(status: ActivityCompletionStatus) -> Option<Self> {
+        let tracking = match status.tracking {
+            0 => return None,
+            1 => CompletionTracking::Manual,
+            _ => CompletionTracking::Automatic,
+        };
+        Some(Self {
+            cmid: status.cmid,
+            module_type: status.modname,
+            instance: status.instance,
+            tracking,
+            state: CompletionState::from_moodle(status.state),
+            completed_at: (status.timecompleted != 0).then_some(status.timecompleted),
+            overridden: status.overrideby.is_some(),
+        })
+    }
+}
+
+impl From<CourseCompletionStatus> for CourseCriteriaResponse {
+    fn from(status: CourseCompletionStatus) -> Self {
+        Self {
+            completed: status.completed,
+            requires_all: status.aggregation != 2,
+            criteria: status
+                .completions
+                .into_iter()
+                .map(|c| CriterionResponse {
+                    title: c.title,
+                    status: c.status,
+                    complete: c.complete,
+                    completed_at: c.timecompleted.filter(|&t| t != 0),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_course_completion(
+    moodle: Extension<moodle::Client>,
+    Path(course_id): Path<u64>,
+) -> Result<Json<CourseCompletionResponse>, CompletionError> {
+    Ok(Json(course_completion(&moodle, course_id).await?))
+}
+
+/// Completion of a course's activities, counted by mita rather than taken
+/// from moodle's cached course progress, which lags behind.
+pub async fn course_completion(
+    moodle: &moodle::Client,
+    course_id: u64,
+) -> Result<CourseCompletionResponse, MoodleError> {
+    let params = CompletionStatusParams {
+        courseid: course_id,
+        userid: moodle.user_id(),
+    };
+    let (activities, course) = futures::join!(
+        moodle.call::<GetActivitiesCompletionStatus>(&params),
+        moodle.call::<GetCourseCompletionStatus>(&params),
+    );
+
+    let activities: Vec<_> = activities?
+        .statuses
+        .into_iter()
+        .filter_map(ActivityCompletionResponse::new)
+        .collect();
+    let course = match course {
+        Ok(res) => Some(CourseCriteriaResponse::from(res.completionstatus)),
+        Err(MoodleError::Api(e))
+            if matches!(
+                e.kind,
+                MoodleApiErrorKind::CompletionNotEnabled | MoodleApiErrorKind::NoCriteriaSet
+            ) =>
+        {
+            None
+        }
+        Err(e) => return Err(e),
+    };
+
+    let total = activities.len() as u32;
+    let completed = activities.iter().filter(|a| a.state.is_complete()).count() as u32;
+
+    Ok(CourseCompletionResponse {
+        course_id,
+        activities,
+        completed,
+        total,
+        percentage: percentage(completed, total),
+        course,
+    })
+}
+
+fn percentage(completed: u32, total: u32) -> Option<f64> {
+    (total != 0).then(|| (f64::from(completed) / f64::from(total) * 10000.0).round() / 100.0)
+}
+
+#[derive(Error, Debug)]
+pub enum CompletionError {
+    #[error("error from moodle")]
+    Moodle(#[from] MoodleError),
+}
+
+impl IntoResponse for CompletionError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            CompletionError::Moodle(e) => e.status(),
+        };
+        tracing::error!(service = "moodle", %status, error = ?self);
+        status.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn untracked_activities_are_skipped() {
+        let statuses: Vec<ActivityCompletionStatus> = serde_json::from_value(json!([
+            {"cmid": 1, "modname": "assign", "instance": 1, "state": 2, "timecompleted": 50, "tracking": 2},
+            {"cmid": 2, "modname": "label", "instance": 2, "state": 0, "timecompleted": 0, "tracking": 0},
+            {"cmid": 3, "modname": "resource", "instance": 3, "state": 0, "timecompleted": 0, "tracking": 1, "overrideby": null},
+        ]))
+        .unwrap();
+        let activities: Vec<_> = statuses
+            .into_iter()
+            .filter_map(ActivityCompletionResponse::new)
+            .collect();
+
+        assert_eq!(activities.len(), 2);
+        assert_eq!(activities[0].state, CompletionState::CompletePass);
+        assert_eq!(activities[1].tracking, CompletionTracking::Manual);
+        assert!(!activities[1].overridden);
+    }
+
+    #[test]
+    fn percentage_of_tracked_activities() {
+        assert_eq!(percentage(0, 0), None);
+        assert_eq!(percentage(1, 3), Some(33.33));
+        assert_eq!(percentage(3, 3), Some(100.0));
+    }
+}
@@ -0,0 +1,37 @@
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use serde::Deserialize;
+
+use super::get::CompletionError;
+use crate::moodle::{
+    self,
+    completion::{UpdateActivityCompletionParams, UpdateActivityCompletionStatusManually},
+    error::{MoodleError, MoodleWarning},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct CompletionBody {
+    pub completed: bool,
+}
+
+/// Marks an activity with manual completion as done or not done.
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn set_activity_completion(
+    moodle: Extension<moodle::Client>,
+    Path(cmid): Path<u64>,
+    Json(body): Json<CompletionBody>,
+) -> Result<StatusCode, CompletionError> {
+    let res = moodle
+        .call::<UpdateActivityCompletionStatusManually>(&UpdateActivityCompletionParams {
+            cmid,
+            completed: body.completed,
+        })
+        .await?;
+    moodle.invalidate_cache();
+    MoodleWarning::check(res.warnings).map_err(MoodleError::from)?;
+    if !res.status {
+        return Err(MoodleError::from(eyre::eyre!("moodle didn't update completion")).into());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,290 @@
+use axum::{
+    extract::Path,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    html,
+    moodle::{
+        self,
+        contents::{CompletionData, Content, GetContents, GetContentsParams, Module, Section},
+        error::MoodleError,
+    },
+};
+
+#[derive(Debug, Serialize)]
+pub struct SectionResponse {
+    pub id: u64,
+    /// Position in the course, 0 is the general section.
+    pub number: u32,
+    pub name: String,
+    /// Plain text.
+    pub summary: Option<String>,
+    pub visible: bool,
+    pub modules: Vec<ModuleResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModuleResponse {
+    pub id: u64,
+    pub instance: u64,
+    pub name: String,
+    /// `resource`, `url`, `page`, `assign`, `quiz`, `forum`, ...
+    pub module_type: String,
+    /// Page of the module on moodle.
+    pub url: Option<String>,
+    /// Target of `url` modules.
+    pub external_url: Option<String>,
+    /// Plain text.
+    pub description: Option<String>,
+    pub visible: bool,
+    /// Why the module can't be accessed yet, in plain text.
+    pub restriction: Option<String>,
+    pub completion: Option<CompletionResponse>,
+    pub files: Vec<FileResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompletionResponse {
+    pub tracking: CompletionTracking,
+    pub state: CompletionState,
+    pub completed_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompletionTracking {
+    Manual,
+    Automatic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompletionState {
+    Incomplete,
+    Complete,
+    CompletePass,
+    CompleteFail,
+}
+
+impl CompletionState {
+    /// Moodle's `COMPLETION_*` constants.
+    pub fn from_moodle(state: u8) -> Self {
+        match state {
+            1 => CompletionState::Complete,
+            2 => CompletionState::CompletePass,
+            3 => CompletionState::CompleteFail,
+            _ => CompletionState::Incomplete,
+        }
+    }
+
+    pub fn is_complete(self) -> bool {
+        self != CompletionState::Incomplete && self != CompletionState::CompleteFail
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileResponse {
+    pub filename: String,
+    /// Folder inside the module, `/` for most files.
+    pub path: String,
+    pub size: u64,
+    pub mime_type: Option<String>,
+    /// Moodle pluginfile url, download it through `/files`.
+    pub url: Option<String>,
+    pub modified_at: Option<i64>,
+}
+
+impl From<Section> for SectionResponse {
+    fn from(section: Section) -> Self {
+        Self {
+            id: section.id,
+            number: section.section,
+            name: section.name,
+            summary: text(&section.summary),
+            visible: section.visible && section.uservisible,
+            modules: section
+                .modules
+                .into_iter()
+                .map(ModuleResponse::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<Module> for ModuleResponse {
+    fn from(module: Module) -> Self {
+        let completion = match module.completion {
+            0 => None,
+            tracking => Some(CompletionResponse::new(tracking, module.completiondata)),
+        };
+        let external_url = match module.modname.as_str() {
+            "url" => module
+                .contents
+                .iter()
+                .find(|c| c.kind == "url")
+                .and_then(|c| c.fileurl.clone()),
+            _ => None,
+        };
+
+        Self {
+            id: module.id,
+            instance: module.instance,
+            name: module.name,
+            module_type: module.modname,
+            url: module.url,
+            external_url,
+            description: module.description.as_deref().and_then(text),
+            visible: module.visible && module.uservisible,
+            restriction: module.availabilityinfo.as_deref().and_then(text),
+            completion,
+            files: module
+                .contents
+                .into_iter()
+                .filter(|c| c.kind == "file")
+                .map(FileResponse::from)
+                .collect(),
+        }
+    }
+}
+
+impl CompletionResponse {
+    fn new(tracking: u8, data: Option<CompletionData>) -> Self {
+        let state = data.as_ref().map_or(CompletionState::Incomplete, |d| {
+            CompletionState::from_moodle(d.state)
+        });
+        Self {
+            tracking: match tracking {
+                1 => CompletionTracking::Manual,
+                _ => CompletionTracking::Automatic,
+            },
+            state,
+            completed_at: data.map(|d| d.timecompleted).filter(|&t| t != 0),
+        }
+    }
+}
+
+impl From<Content> for FileResponse {
+    fn from(content: Content) -> Self {
+        Self {
+            filename: content.filename,
+            path: content.filepath.unwrap_or_else(|| "/".into()),
+            size: content.filesize,
+            mime_type: content.mimetype,
+            url: content.fileurl,
+            modified_at: (content.timemodified != 0).then_some(content.timemodified),
+        }
+    }
+}
+
+fn text(html: &str) -> Option<String> {
+    Some(html::to_text(html)).filter(|t| !t.is_empty())
+}
+
+/// Sections of a course with their modules and files.
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_course_contents(
+    moodle: Extension<moodle::Client>,
+    Path(course_id): Path<u64>,
+) -> Result<Json<Vec<SectionResponse>>, ContentsError> {
+    let sections = moodle
+        .call::<GetContents>(&GetContentsParams {
+            courseid: course_id,
+        })
+        .await?;
+
+    Ok(Json(
+        sections.into_iter().map(SectionResponse::from).collect(),
+    ))
+}
+
+#[derive(Error, Debug)]
+pub enum ContentsError {
+    #[error("error getting course contents from moodle")]
+    Moodle(#[from] MoodleError),
+}
+
+impl IntoResponse for ContentsError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            ContentsError::Moodle(e) => e.status(),
+        };
+        tracing::error!(service = "moodle", %status, error = ?self);
+        status.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{CompletionState, CompletionTracking, SectionResponse};
+    use crate::moodle::contents::Section;
+
+    #[test]
+    fn normalizes_section() -> eyre::Result<()> {
+        let section: Section = serde_json::from_value(json!({
+            "id": 10,
+            "name": "Week 1",
+            "visible": 1,
+            "summary": "<p>Introduction</p>",
+            "section": 1,
+            "uservisible": true,
+            "modules": [
+                {
+                    "id": 100,
+                    "url": "https://e-learning.hcmut.edu.vn/mod/resource/view.php?id=100",
+                    "name": "Slides",
+                    "instance": 5,
+                    "visible": 1,
+                    "uservisible": true,
+                    "modname": "resource",
+                    "completion": 1,
+                    "completiondata": { "state": 1, "timecompleted": 1678000000 },
+                    "contents": [{
+                        "type": "file",
+                        "filename": "week1.pdf",
+                        "filepath": "/",
+                        "filesize": 1024,
+                        "fileurl": "https://e-learning.hcmut.edu.vn/webservice/pluginfile.php/1/mod_resource/content/1/week1.pdf?forcedownload=1",
+                        "timemodified": 1677000000,
+                        "mimetype": "application/pdf",
+                    }],
+                },
+                {
+                    "id": 101,
+                    "name": "Course site",
+                    "instance": 6,
+                    "visible": 0,
+                    "modname": "url",
+                    "availabilityinfo": "Not available unless: <strong>Quiz 1</strong> is complete",
+                    "contents": [{ "type": "url", "fileurl": "https://example.com" }],
+                },
+            ],
+        }))?;
+
+        let section = SectionResponse::from(section);
+
+        assert_eq!(section.summary.as_deref(), Some("Introduction"));
+        let [resource, url] = &section.modules[..] else {
+            panic!("expected two modules");
+        };
+        let completion = resource.completion.as_ref().unwrap();
+        assert_eq!(completion.tracking, CompletionTracking::Manual);
+        assert_eq!(completion.state, CompletionState::Complete);
+        assert_eq!(resource.files[0].filename, "week1.pdf");
+        assert!(!url.visible);
+        assert!(url.files.is_empty());
+        assert_eq!(url.external_url.as_deref(), Some("https://example.com"));
+        assert_eq!(
+            url.restriction.as_deref(),
+            Some("Not available unless: Quiz 1 is complete")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+use axum::{
+    extract::{Path, Query},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    html,
+    moodle::{
+        self,
+        error::MoodleError,
+        message::{
+            Conversation, ConversationMember, GetConversationMessages,
+            GetConversationMessagesParams, GetConversations, GetConversationsParams, Message,
+        },
+    },
+};
+
+const MAX_LIMIT: u32 = 100;
+const DEFAULT_LIMIT: u32 = 50;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PageQuery {
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+impl PageQuery {
+    fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversationResponse {
+    pub id: u64,
+    /// The group name, or the other members' names for private conversations.
+    pub name: String,
+    pub kind: ConversationKind,
+    pub member_count: u32,
+    pub members: Vec<MemberResponse>,
+    pub unread_count: u32,
+    pub favourite: bool,
+    pub muted: bool,
+    pub last_message: Option<MessageResponse>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversationKind {
+    Private,
+    Group,
+    /// Notes to self.
+    #[serde(rename = "self")]
+    SelfConversation,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberResponse {
+    pub id: u64,
+    pub full_name: String,
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageResponse {
+    pub id: u64,
+    pub from: u64,
+    /// Whether the user sent the message.
+    pub own: bool,
+    /// Plain text.
+    pub text: String,
+    pub sent_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessagesResponse {
+    pub conversation_id: u64,
+    pub members: Vec<MemberResponse>,
+    /// Newest first.
+    pub messages: Vec<MessageResponse>,
+}
+
+impl From<ConversationMember> for MemberResponse {
+    fn from(member: ConversationMember) -> Self {
+        Self {
+            id: member.id,
+            full_name: member.fullname,
+            avatar_url: member.profileimageurl,
+        }
+    }
+}
+
+impl MessageResponse {
+    pub fn new(message: Message, user_id: u64) -> Self {
+        Self {
+            id: message.id,
+            from: message.useridfrom,
+            own: message.useridfrom == user_id,
+            text: html::to_text(&message.text),
+            sent_at: message.timecreated,
+        }
+    }
+}
+
+impl ConversationResponse {
+    fn new(conversation: Conversation, user_id: u64) -> Self {
+        let kind = match conversation.kind {
+            2 => ConversationKind::Group,
+            3 => ConversationKind::SelfConversation,
+            _ => ConversationKind::Private,
+        };
+        let name = conversation
+            .name
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| {
+                conversation
+                    .members
+                    .iter()
+                    .map(|m| m.fullname.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            });
+        Self {
+            id: conversation.id,
+            name,
+            kind,
+            member_count: conversation.membercount,
+            members: conversation
+                .members
+                .into_iter()
+                .map(MemberResponse::from)
+                .collect(),
+            unread_count: conversation.unreadcount.unwrap_or_default(),
+            favourite: conversation.isfavourite,
+            muted: conversation.ismuted,
+            last_message: conversation
+                .messages
+                .into_iter()
+                .next()
+                .map(|m| MessageResponse::new(m, user_id)),
+        }
+    }
+}
+
+/// Most recent conversations first.
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_conversations(
+    moodle: Extension<moodle::Client>,
+    query: Query<PageQuery>,
+) -> Result<Json<Vec<ConversationResponse>>, ConversationsError> {
+    let res = moodle
+        .call::<GetConversations>(&GetConversationsParams {
+            userid: moodle.user_id(),
+            limitfrom: query.offset.unwrap_or(0),
+            limitnum: query.limit(),
+        })
+        .await?;
+
+    Ok(Json(
+        res.conversations
+            .into_iter()
+            .map(|c| ConversationResponse::new(c, moodle.user_id()))
+            .collect(),
+    ))
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_conversation_messages(
+    moodle: Extension<moodle::Client>,
+    Path(id): Path<u64>,
+    query: Query<PageQuery>,
+) -> Result<Json<MessagesResponse>, ConversationsError> {
+    let res = moodle
+        .call::<GetConversationMessages>(&GetConversationMessagesParams {
+            currentuserid: moodle.user_id(),
+            convid: id,
+            newest: true,
+            limitfrom: query.offset.unwrap_or(0),
+            limitnum: query.limit(),
+        })
+        .await?;
+
+    Ok(Json(MessagesResponse {
+        conversation_id: res.id,
+        members: res.members.into_iter().map(MemberResponse::from).collect(),
+        messages: res
+            .messages
+            .into_iter()
+            .map(|m| MessageResponse::new(m, moodle.user_id()))
+            .collect(),
+    }))
+}
+
+#[derive(Error, Debug)]
+pub enum ConversationsError {
+    #[error("error from moodle")]
+    Moodle(#[from] MoodleError),
+}
+
+impl IntoResponse for ConversationsError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            ConversationsError::Moodle(e) => e.status(),
+        };
+        tracing::error!(service = "moodle", %status, error = ?self);
+        status.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn private_conversations_are_named_after_members() {
+        let conversation: Conversation = serde_json::from_value(json!({
+            "id": 3,
+            "name": "",
+            "type": 1,
+            "membercount": 2,
+            "unreadcount": null,
+            "members": [{"id": 7, "fullname": "Nguyen Van A"}],
+            "messages": [{"id": 9, "useridfrom": 2, "text": "<p>hi</p>", "timecreated": 10}],
+        }))
+        .unwrap();
+        let res = ConversationResponse::new(conversation, 2);
+        assert_eq!(res.name, "Nguyen Van A");
+        assert_eq!(res.kind, ConversationKind::Private);
+        assert_eq!(res.unread_count, 0);
+        let last = res.last_message.unwrap();
+        assert!(last.own);
+        assert_eq!(last.text, "hi");
+    }
+}
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    http::{header::RETRY_AFTER, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::get::MessageResponse;
+use crate::{
+    app_state::AppState,
+    moodle::{
+        self,
+        error::MoodleError,
+        message::{
+            NewMessage, SendMessagesToConversation, SendMessagesToConversationParams, FORMAT_PLAIN,
+        },
+    },
+    oidc::Claims,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct MessageBody {
+    /// Plain text.
+    pub text: String,
+}
+
+impl MessageBody {
+    /// Checks the message and the sender's rate limit, so that neither
+    /// empty nor oversized messages nor floods reach moodle.
+    pub fn validate(self, state: &AppState, claims: &Claims) -> Result<String, MessagesError> {
+        let config = &state.config.messages;
+        let text = self.text.trim();
+        if text.is_empty() {
+            return Err(MessagesError::Empty);
+        }
+        if text.chars().count() > config.max_length {
+            return Err(MessagesError::TooLong(config.max_length));
+        }
+
+        state
+            .message_limiter
+            .check(
+                &claims.sub,
+                config.rate_limit,
+                Duration::from_secs(config.rate_window),
+            )
+            .map_err(MessagesError::RateLimited)?;
+
+        Ok(text.into())
+    }
+}
+
+#[axum::debug_handler(state = AppState)]
+#[tracing::instrument(skip(claims, moodle, state, body))]
+pub async fn send_conversation_message(
+    claims: Extension<Claims>,
+    moodle: Extension<moodle::Client>,
+    state: State<AppState>,
+    Path(id): Path<u64>,
+    Json(body): Json<MessageBody>,
+) -> Result<(StatusCode, Json<MessageResponse>), MessagesError> {
+    let text = body.validate(&state, &claims)?;
+
+    let sent = moodle
+        .call::<SendMessagesToConversation>(&SendMessagesToConversationParams {
+            conversationid: id,
+            messages: vec![NewMessage {
+                text,
+                textformat: FORMAT_PLAIN,
+            }],
+        })
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| eyre::eyre!("moodle returned no sent message"))
+        .map_err(MoodleError::from)?;
+    moodle.invalidate_cache();
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MessageResponse::new(sent, moodle.user_id())),
+    ))
+}
+
+#[derive(Error, Debug)]
+pub enum MessagesError {
+    #[error("message is empty")]
+    Empty,
+    #[error("message is longer than {0} characters")]
+    TooLong(usize),
+    #[error("too many messages, retry after {0:?}")]
+    RateLimited(Duration),
+    #[error("error from moodle")]
+    Moodle(#[from] MoodleError),
+}
+
+impl IntoResponse for MessagesError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            MessagesError::Empty => StatusCode::BAD_REQUEST,
+            MessagesError::TooLong(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            MessagesError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            MessagesError::Moodle(e) => e.status(),
+        };
+        let service = match &self {
+            MessagesError::Moodle(_) => "moodle",
+            _ => "mita",
+        };
+        tracing::error!(%service, %status, error = ?self);
+        match self {
+            MessagesError::RateLimited(retry_after) => {
+                // round up, retrying early would be rejected again
+                let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+                (status, [(RETRY_AFTER, secs.to_string())]).into_response()
+            }
+            _ => status.into_response(),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use axum::{
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::moodle::{self, course::Course, error::MoodleError};
+
+#[derive(Serialize)]
+pub struct CourseResponse {
+    id: u64,
+    shortname: String,
+    fullname: String,
+    category: Option<u64>,
+    progress: Option<f64>,
+    start_date: i64,
+    end_date: Option<i64>,
+    hidden: bool,
+    favourite: bool,
+}
+
+impl From<Course> for CourseResponse {
+    fn from(course: Course) -> Self {
+        Self {
+            id: course.id,
+            shortname: course.shortname,
+            fullname: course.fullname,
+            category: course.category,
+            progress: course.progress,
+            start_date: course.startdate,
+            // moodle uses 0 for courses without an end date
+            end_date: (course.enddate != 0).then_some(course.enddate),
+            hidden: course.hidden,
+            favourite: course.isfavourite,
+        }
+    }
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_courses(
+    moodle: Extension<moodle::Client>,
+) -> Result<Json<Vec<CourseResponse>>, CoursesError> {
+    let courses = moodle.get_courses().await?;
+
+    Ok(Json(
+        courses.into_iter().map(CourseResponse::from).collect(),
+    ))
+}
+
+#[derive(Error, Debug)]
+pub enum CoursesError {
+    #[error("error getting courses from moodle")]
+    Moodle(#[from] MoodleError),
+}
+
+impl IntoResponse for CoursesError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            CoursesError::Moodle(e) => e.status(),
+        };
+        tracing::error!(service = "moodle", %status, error = ?self);
+        status.into_response()
+    }
+}
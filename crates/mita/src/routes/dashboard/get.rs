@@ -0,0 +1,269 @@
+use std::cmp::Reverse;
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use futures::{stream, StreamExt};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    moodle::{self, course::Course, error::MoodleError},
+    routes::{
+        completion::get::course_completion,
+        courses::get::CourseResponse,
+        deadlines::get::{upcoming_deadlines, Deadline, DeadlinesQuery},
+        grades::get::{course_grade_items, GradeItemResponse},
+        notifications::get::{notifications, NotificationResponse, NotificationsQuery},
+    },
+};
+
+/// Courses whose completion and grades are fetched at once.
+const CONCURRENCY: usize = 4;
+const DEADLINES: u32 = 5;
+const NOTIFICATIONS: u32 = 10;
+const RECENT_GRADES: usize = 5;
+
+/// Each section holds either its data or why it couldn't be loaded, so that
+/// one failing moodle function doesn't take down the whole dashboard.
+#[derive(Serialize)]
+pub struct DashboardResponse {
+    pub courses: Section<Vec<DashboardCourse>>,
+    pub deadlines: Section<Vec<Deadline>>,
+    pub notifications: Section<UnreadNotifications>,
+    pub recent_grades: Section<Vec<RecentGrade>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Section<T> {
+    Data(T),
+    Error(SectionError),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionError {
+    /// The status the section's own endpoint would have responded with.
+    pub status: u16,
+    pub message: &'static str,
+}
+
+impl SectionError {
+    fn new(e: &MoodleError) -> Self {
+        let status = e.status();
+        tracing::error!(service = "moodle", %status, error = ?e, "error loading dashboard section");
+        Self {
+            status: status.as_u16(),
+            message: status.canonical_reason().unwrap_or("error from moodle"),
+        }
+    }
+}
+
+impl<T> From<Result<T, MoodleError>> for Section<T> {
+    fn from(res: Result<T, MoodleError>) -> Self {
+        match res {
+            Ok(data) => Section::Data(data),
+            Err(e) => Section::Error(SectionError::new(&e)),
+        }
+    }
+}
+
+impl<T> Section<T> {
+    fn error(&self) -> Option<&SectionError> {
+        match self {
+            Section::Data(_) => None,
+            Section::Error(e) => Some(e),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DashboardCourse {
+    #[serde(flatten)]
+    pub course: CourseResponse,
+    /// Completed tracked activities, from 0 to 100. `None` when the course
+    /// tracks none or its completion couldn't be loaded.
+    pub completion: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnreadNotifications {
+    pub unread_count: u32,
+    /// Newest first.
+    pub notifications: Vec<NotificationResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecentGrade {
+    pub course_id: u64,
+    pub course_name: String,
+    #[serde(flatten)]
+    pub item: GradeItemResponse,
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_dashboard(
+    moodle: Extension<moodle::Client>,
+) -> Result<Json<DashboardResponse>, DashboardError> {
+    let deadlines_query = DeadlinesQuery {
+        limit: Some(DEADLINES),
+        ..Default::default()
+    };
+    let notifications_query = NotificationsQuery {
+        limit: Some(NOTIFICATIONS),
+        ..Default::default()
+    };
+    let (courses_and_grades, deadlines, notifications) = futures::join!(
+        courses_and_grades(&moodle),
+        upcoming_deadlines(&moodle, &deadlines_query),
+        async {
+            let res = notifications(&moodle, &notifications_query).await?;
+            Ok(UnreadNotifications {
+                unread_count: res.unread_count,
+                notifications: res.notifications.into_iter().filter(|n| !n.read).collect(),
+            })
+        },
+    );
+
+    let (courses, recent_grades) = match courses_and_grades {
+        Ok((courses, recent_grades)) => (Section::Data(courses), recent_grades.into()),
+        // both sections report the same failure
+        Err(e) => {
+            let e = SectionError::new(&e);
+            (Section::Error(e.clone()), Section::Error(e))
+        }
+    };
+    let dashboard = DashboardResponse {
+        courses,
+        deadlines: deadlines.into(),
+        notifications: notifications.into(),
+        recent_grades,
+    };
+
+    // nothing to show, most likely the same problem everywhere
+    let errors = [
+        dashboard.courses.error(),
+        dashboard.deadlines.error(),
+        dashboard.notifications.error(),
+        dashboard.recent_grades.error(),
+    ];
+    if let [Some(e), Some(_), Some(_), Some(_)] = errors {
+        return Err(DashboardError(
+            StatusCode::from_u16(e.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        ));
+    }
+
+    Ok(Json(dashboard))
+}
+
+/// Both sections need every course's data, so they share one fan-out.
+/// Courses whose grades can't be read, e.g. because the course hides its
+/// grade report, are left out of the recent grades.
+async fn courses_and_grades(
+    moodle: &moodle::Client,
+) -> Result<(Vec<DashboardCourse>, Result<Vec<RecentGrade>, MoodleError>), MoodleError> {
+    let courses = moodle.get_courses().await?;
+
+    let per_course: Vec<_> = stream::iter(courses)
+        .map(|course| async move {
+            let (completion, grades) = futures::join!(
+                course_completion(moodle, course.id),
+                course_grade_items(moodle, course.id),
+            );
+            let completion = completion
+                .map_err(|e| tracing::warn!(course_id = course.id, error = ?e, "error getting completion"))
+                .ok()
+                .and_then(|c| c.percentage);
+            (course, completion, grades)
+        })
+        .buffered(CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut dashboard_courses = Vec::with_capacity(per_course.len());
+    let mut recent_grades = vec![];
+    let mut grades_error = None;
+    let mut any_grades = false;
+    for (course, completion, grades) in per_course {
+        match grades {
+            Ok(items) => {
+                any_grades = true;
+                recent_grades.extend(recent(&course, items));
+            }
+            Err(e) => {
+                tracing::warn!(course_id = course.id, error = ?e, "error getting grades");
+                grades_error = grades_error.or(Some(e));
+            }
+        }
+        dashboard_courses.push(DashboardCourse {
+            course: CourseResponse::from(course),
+            completion,
+        });
+    }
+    recent_grades.sort_by_key(|g| Reverse(g.item.graded_at));
+    recent_grades.truncate(RECENT_GRADES);
+
+    let recent_grades = match grades_error {
+        Some(e) if !any_grades => Err(e),
+        _ => Ok(recent_grades),
+    };
+    Ok((dashboard_courses, recent_grades))
+}
+
+/// Graded activities of a course.
+fn recent(
+    course: &Course,
+    items: Vec<GradeItemResponse>,
+) -> impl Iterator<Item = RecentGrade> + '_ {
+    items
+        .into_iter()
+        .filter(|item| item.item_type == "mod" && item.graded_at.is_some() && !item.hidden)
+        .map(|item| RecentGrade {
+            course_id: course.id,
+            course_name: course.fullname.clone(),
+            item,
+        })
+}
+
+/// Every section failed.
+#[derive(Error, Debug)]
+#[error("every dashboard section failed")]
+pub struct DashboardError(StatusCode);
+
+impl IntoResponse for DashboardError {
+    fn into_response(self) -> Response {
+        let status = self.0;
+        tracing::error!(service = "moodle", %status, error = ?self);
+        status.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Section;
+    use crate::moodle::error::{MoodleApiError, MoodleError};
+
+    #[test]
+    fn failed_sections_carry_their_status() {
+        let e: MoodleApiError = serde_json::from_value(json!({
+            "errorcode": "servicenotavailable",
+            "message": "Web service is not available",
+        }))
+        .unwrap();
+        let section = Section::<Vec<u32>>::from(Err(MoodleError::from(e)));
+
+        assert_eq!(
+            serde_json::to_value(section).unwrap(),
+            json!({"error": {"status": 503, "message": "Service Unavailable"}})
+        );
+        assert_eq!(
+            serde_json::to_value(Section::from(Ok::<_, MoodleError>(vec![1]))).unwrap(),
+            json!({"data": [1]})
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::Query,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use url::Url;
+
+use crate::{
+    clock::now,
+    moodle::{
+        self,
+        assign::{Assignment, GetAssignments, GetAssignmentsParams},
+        calendar::{ActionEvent, GetActionEventsByTimesort, GetActionEventsByTimesortParams},
+        error::MoodleError,
+    },
+};
+
+/// Moodle refuses to return more than this many action events at once.
+const EVENTS_PAGE: u32 = 50;
+
+/// Most deadlines returned by one request.
+const MAX_LIMIT: u32 = 50;
+
+/// Deadlines returned when the request doesn't ask for a number.
+const DEFAULT_LIMIT: u32 = 20;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DeadlinesQuery {
+    /// Unix timestamp, defaults to now.
+    pub from: Option<i64>,
+    /// Unix timestamp, unbounded by default.
+    pub to: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Deadline {
+    pub id: String,
+    pub name: String,
+    pub course_id: u64,
+    pub course_name: String,
+    pub activity_type: Option<String>,
+    pub due: i64,
+    pub cutoff: Option<i64>,
+    pub submission_status: SubmissionStatus,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmissionStatus {
+    Pending,
+    Overdue,
+    /// The cut-off date has passed, moodle won't accept a submission anymore.
+    Closed,
+    Submitted,
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_deadlines(
+    moodle: Extension<moodle::Client>,
+    query: Query<DeadlinesQuery>,
+) -> Result<Json<Vec<Deadline>>, DeadlinesError> {
+    let query = DeadlinesQuery {
+        limit: Some(query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT)),
+        ..query.0
+    };
+    Ok(Json(upcoming_deadlines(&moodle, &query).await?))
+}
+
+/// Merges the user's calendar action events with the assignments of every
+/// enrolled course, sorted by due time. The limit isn't clamped, callers
+/// pick their own bound.
+pub async fn upcoming_deadlines(
+    moodle: &moodle::Client,
+    query: &DeadlinesQuery,
+) -> Result<Vec<Deadline>, MoodleError> {
+    let now = now();
+    let from = query.from.unwrap_or(now);
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+
+    let courses = moodle.get_courses().await?;
+    let course_names = courses
+        .iter()
+        .map(|c| (c.id, c.fullname.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let assignments_params = GetAssignmentsParams {
+        courseids: courses.iter().map(|c| c.id).collect(),
+    };
+    let ((events, read_until), assignments) = futures::try_join!(
+        action_events(moodle, from, query.to, limit),
+        moodle.call::<GetAssignments>(&assignments_params),
+    )?;
+
+    let assignments = assignments
+        .courses
+        .into_iter()
+        .flat_map(|c| c.assignments)
+        .filter(|a| {
+            a.duedate != 0 && a.duedate >= from && query.to.is_none_or(|to| a.duedate <= to)
+        });
+
+    let mut deadlines = merge(
+        events,
+        read_until,
+        assignments,
+        &course_names,
+        moodle.base_url(),
+        now,
+    );
+    deadlines.truncate(limit as usize);
+
+    Ok(deadlines)
+}
+
+/// Up to `limit` action events, a page at a time. Also returns the timesort
+/// from which events may be missing, `None` if moodle has no more.
+async fn action_events(
+    moodle: &moodle::Client,
+    from: i64,
+    to: Option<i64>,
+    limit: u32,
+) -> Result<(Vec<ActionEvent>, Option<i64>), MoodleError> {
+    let mut events: Vec<ActionEvent> = vec![];
+    while (events.len() as u32) < limit {
+        let limitnum = (limit - events.len() as u32).min(EVENTS_PAGE);
+        let page = moodle
+            .call::<GetActionEventsByTimesort>(&GetActionEventsByTimesortParams {
+                timesortfrom: from,
+                timesortto: to,
+                limitnum,
+                aftereventid: events.last().map(|e| e.id),
+                limittononsuspendedevents: true,
+            })
+            .await?
+            .events;
+        let last_page = (page.len() as u32) < limitnum;
+        events.extend(page);
+        if last_page {
+            return Ok((events, None));
+        }
+    }
+    let read_until = events.last().map_or(from, |e| e.timesort);
+    Ok((events, Some(read_until)))
+}
+
+/// `read_until` is where [`action_events`] stopped, past it an assignment
+/// without a pending event may still have one.
+fn merge(
+    events: Vec<ActionEvent>,
+    read_until: Option<i64>,
+    assignments: impl IntoIterator<Item = Assignment>,
+    course_names: &HashMap<u64, String>,
+    base_url: &Url,
+    now: i64,
+) -> Vec<Deadline> {
+    // assignments only show up as action events while there is still
+    // something to submit
+    let mut pending_assignments = HashMap::new();
+    let mut deadlines = Vec::new();
+
+    for event in events {
+        let assign_id = event
+            .instance
+            .filter(|_| event.modulename.as_deref() == Some("assign"));
+        match assign_id {
+            Some(id) => {
+                pending_assignments.insert(id, event);
+            }
+            None => deadlines.push(Deadline::from_event(event, course_names, now)),
+        }
+    }
+
+    for assignment in assignments {
+        let pending = pending_assignments.remove(&assignment.id).is_some();
+        // events at `read_until` itself may be on the next page
+        if !pending && read_until.is_some_and(|until| assignment.duedate >= until) {
+            continue;
+        }
+        let course_name = course_names
+            .get(&assignment.course)
+            .cloned()
+            .unwrap_or_default();
+        let cutoff = (assignment.cutoffdate != 0).then_some(assignment.cutoffdate);
+        let url = base_url
+            .join(&format!("mod/assign/view.php?id={}", assignment.cmid))
+            .ok()
+            .map(String::from);
+
+        deadlines.push(Deadline {
+            id: format!("assign-{}", assignment.id),
+            name: assignment.name,
+            course_id: assignment.course,
+            course_name,
+            activity_type: Some("assign".into()),
+            due: assignment.duedate,
+            cutoff,
+            submission_status: status(now, assignment.duedate, cutoff, pending),
+            url,
+        });
+    }
+
+    // pending assignment events that mod_assign didn't return
+    deadlines.extend(
+        pending_assignments
+            .into_values()
+            .map(|event| Deadline::from_event(event, course_names, now)),
+    );
+
+    deadlines.sort_by(|a, b| a.due.cmp(&b.due).then_with(|| a.id.cmp(&b.id)));
+    deadlines
+}
+
+impl Deadline {
+    fn from_event(event: ActionEvent, course_names: &HashMap<u64, String>, now: i64) -> Self {
+        let (course_id, course_name) = match event.course {
+            Some(course) => (course.id, course.fullname),
+            None => (0, String::new()),
+        };
+        let course_name = match course_name.is_empty() {
+            true => course_names.get(&course_id).cloned().unwrap_or_default(),
+            false => course_name,
+        };
+        let pending = event.action.is_some();
+
+        Self {
+            id: format!("event-{}", event.id),
+            name: event.name,
+            course_id,
+            course_name,
+            activity_type: event.modulename,
+            due: event.timesort,
+            cutoff: None,
+            submission_status: status(now, event.timesort, None, pending),
+            url: event.url,
+        }
+    }
+}
+
+fn status(now: i64, due: i64, cutoff: Option<i64>, pending: bool) -> SubmissionStatus {
+    if !pending {
+        SubmissionStatus::Submitted
+    } else if cutoff.is_some_and(|cutoff| now > cutoff) {
+        SubmissionStatus::Closed
+    } else if now > due {
+        SubmissionStatus::Overdue
+    } else {
+        SubmissionStatus::Pending
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DeadlinesError {
+    #[error("error getting deadlines from moodle")]
+    Moodle(#[from] MoodleError),
+}
+
+impl IntoResponse for DeadlinesError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            DeadlinesError::Moodle(e) => e.status(),
+        };
+        tracing::error!(service = "moodle", %status, error = ?self);
+        status.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::moodle::{
+        assign::Assignment,
+        calendar::{ActionEvent, EventAction, EventCourse},
+    };
+
+    use super::{merge, SubmissionStatus};
+
+    fn event(id: u64, modulename: &str, instance: u64, timesort: i64) -> ActionEvent {
+        ActionEvent {
+            id,
+            name: format!("event {id}"),
+            modulename: Some(modulename.into()),
+            instance: Some(instance),
+            timesort,
+            course: Some(EventCourse {
+                id: 1,
+                fullname: "Course".into(),
+            }),
+            action: Some(EventAction {
+                name: "Add submission".into(),
+                actionable: true,
+            }),
+            url: None,
+        }
+    }
+
+    fn assignment(id: u64, duedate: i64, cutoffdate: i64) -> Assignment {
+        Assignment {
+            id,
+            cmid: id + 100,
+            course: 1,
+            name: format!("assignment {id}"),
+            duedate,
+            cutoffdate,
+        }
+    }
+
+    #[test]
+    fn merges_and_sorts() {
+        let url = "https://moodle.test".parse().unwrap();
+        let names = HashMap::from([(1, "Course".to_string())]);
+        let deadlines = merge(
+            vec![event(1, "quiz", 9, 300), event(2, "assign", 10, 200)],
+            None,
+            vec![assignment(10, 200, 0), assignment(11, 100, 0)],
+            &names,
+            &url,
+            0,
+        );
+
+        let ids = deadlines.iter().map(|d| d.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, ["assign-11", "assign-10", "event-1"]);
+        assert_eq!(deadlines[0].submission_status, SubmissionStatus::Submitted);
+        assert_eq!(deadlines[1].submission_status, SubmissionStatus::Pending);
+        assert_eq!(
+            deadlines[1].url.as_deref(),
+            Some("https://moodle.test/mod/assign/view.php?id=110")
+        );
+    }
+
+    #[test]
+    fn overdue_and_closed() {
+        let url = "https://moodle.test".parse().unwrap();
+        let deadlines = merge(
+            vec![event(1, "assign", 10, 100), event(2, "assign", 11, 100)],
+            None,
+            vec![assignment(10, 100, 0), assignment(11, 100, 150)],
+            &HashMap::new(),
+            &url,
+            200,
+        );
+
+        assert_eq!(deadlines[0].submission_status, SubmissionStatus::Overdue);
+        assert_eq!(deadlines[1].submission_status, SubmissionStatus::Closed);
+    }
+
+    #[test]
+    fn no_submitted_past_read_events() {
+        let url = "https://moodle.test".parse().unwrap();
+        let deadlines = merge(
+            vec![event(1, "quiz", 9, 100), event(2, "quiz", 10, 200)],
+            Some(200),
+            vec![
+                assignment(11, 150, 0),
+                assignment(12, 200, 0),
+                assignment(13, 300, 0),
+            ],
+            &HashMap::new(),
+            &url,
+            0,
+        );
+
+        let ids = deadlines.iter().map(|d| d.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, ["event-1", "assign-11", "event-2"]);
+        assert_eq!(deadlines[1].submission_status, SubmissionStatus::Submitted);
+    }
+}
@@ -0,0 +1,37 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::{
+    app_state::AppState,
+    feed_token::{self, FeedKind, FeedTokenError},
+    oidc::Claims,
+};
+
+#[axum::debug_handler(state = AppState)]
+#[tracing::instrument(skip(claims, state))]
+pub async fn revoke_announcements_feed(
+    claims: Extension<Claims>,
+    state: State<AppState>,
+) -> Result<StatusCode, RevokeAnnouncementsFeedError> {
+    match feed_token::revoke(&state.pool, &claims.sub, FeedKind::Announcements).await? {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Err(FeedTokenError::NotFound.into()),
+    }
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct RevokeAnnouncementsFeedError(#[from] FeedTokenError);
+
+impl IntoResponse for RevokeAnnouncementsFeedError {
+    fn into_response(self) -> Response {
+        let status = self.0.status();
+        tracing::error!(service = "mita", %status, error = ?self, "error revoking announcements feed");
+        status.into_response()
+    }
+}
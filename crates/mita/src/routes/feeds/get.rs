@@ -0,0 +1,113 @@
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::{
+    app_state::AppState,
+    atom,
+    clock::now,
+    feed_token::{self, FeedKind, FeedToken, FeedTokenError},
+    html,
+    moodle::{self, error::MoodleError},
+    routes::announcements::get::announcements,
+};
+
+/// Entries in the feed, feed readers keep older ones themselves.
+const FEED_LIMIT: u32 = 50;
+
+#[axum::debug_handler(state = AppState)]
+#[tracing::instrument(skip(state, feed_token))]
+pub async fn get_announcements_feed(
+    state: State<AppState>,
+    Path(feed_token): Path<String>,
+) -> Result<Response, AnnouncementsFeedError> {
+    let feed_token = feed_token
+        .parse::<FeedToken>()
+        .map_err(|_| FeedTokenError::NotFound)?;
+
+    let moodle_token =
+        feed_token::resolve(&state.pool, FeedKind::Announcements, &feed_token).await?;
+    let moodle = moodle::Client::new(
+        &state.http_client,
+        &state.config.moodle,
+        moodle_token,
+        state.moodle_cache.clone(),
+        state.moodle_validations.clone(),
+        // the feed's copy may be older than the registered token
+        None,
+    )
+    .await?;
+
+    let base = &state.config.moodle.url;
+    // tag uris (RFC 4151) stay the same as long as the moodle site does
+    let tag = format!(
+        "tag:{},2023:",
+        base.host_str().unwrap_or("moodle").to_lowercase()
+    );
+
+    let entries = announcements(&moodle, FEED_LIMIT)
+        .await?
+        .into_iter()
+        .map(|announcement| {
+            let discussion = announcement.discussion;
+            let mut link = base.join("mod/forum/discuss.php").ok();
+            if let Some(link) = &mut link {
+                link.query_pairs_mut()
+                    .append_pair("d", &discussion.discussion.to_string());
+            }
+            atom::Entry {
+                id: format!("{tag}discussion/{}", discussion.discussion),
+                title: format!("[{}] {}", announcement.course_name, discussion.subject),
+                author: discussion.userfullname,
+                category: Some(announcement.course_name),
+                link: link.map(String::from),
+                published: discussion.created,
+                updated: discussion.created.max(discussion.timemodified),
+                content: html::sanitize(&discussion.message),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let feed = atom::Feed {
+        id: format!("{tag}announcements/{}", moodle.user_id()),
+        title: "Moodle announcements".into(),
+        updated: entries.iter().map(|e| e.updated).max().unwrap_or_else(now),
+        entries,
+    };
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        atom::render(&feed),
+    )
+        .into_response())
+}
+
+#[derive(Error, Debug)]
+pub enum AnnouncementsFeedError {
+    #[error("error resolving feed token")]
+    FeedToken(#[from] FeedTokenError),
+    #[error("error getting announcements from moodle")]
+    Moodle(#[from] MoodleError),
+}
+
+impl IntoResponse for AnnouncementsFeedError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            AnnouncementsFeedError::FeedToken(e) => e.status(),
+            AnnouncementsFeedError::Moodle(e) => e.status(),
+        };
+        let service = match &self {
+            AnnouncementsFeedError::FeedToken(_) => "mita",
+            AnnouncementsFeedError::Moodle(_) => "moodle",
+        };
+        match status {
+            StatusCode::NOT_FOUND => tracing::info!(%service, %status, error = ?self),
+            _ => tracing::error!(%service, %status, error = ?self),
+        }
+        status.into_response()
+    }
+}
@@ -0,0 +1,180 @@
+use axum::{
+    body::{boxed, StreamBody},
+    extract::Query,
+    http::{header, HeaderMap, HeaderName, HeaderValue},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::moodle::{self, error::MoodleError};
+
+/// Response headers of moodle passed on to the client.
+const PASSTHROUGH: [HeaderName; 6] = [
+    header::CONTENT_TYPE,
+    header::CONTENT_LENGTH,
+    header::CONTENT_RANGE,
+    header::ACCEPT_RANGES,
+    header::LAST_MODIFIED,
+    header::ETAG,
+];
+
+/// Types shown inline, none of them can run scripts on mita's origin.
+const INLINE_TYPES: &[&str] = &[
+    "application/pdf",
+    "image/bmp",
+    "image/gif",
+    "image/jpeg",
+    "image/png",
+    "image/webp",
+];
+
+#[derive(Debug, Deserialize)]
+pub struct FileQuery {
+    /// A pluginfile url from moodle, e.g. a file of `/courses/:id/contents`.
+    pub url: String,
+}
+
+/// Downloads a moodle file with the user's token, so the token never reaches
+/// the client. The body is streamed as it arrives.
+///
+/// Files are uploaded by any course member and served from mita's origin, so
+/// only images and PDFs are shown inline, everything else is an attachment
+/// and nothing may run scripts or be sniffed into another type.
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle, headers))]
+pub async fn get_file(
+    moodle: Extension<moodle::Client>,
+    query: Query<FileQuery>,
+    headers: HeaderMap,
+) -> Result<Response, FilesError> {
+    let url = moodle
+        .pluginfile_url(&query.url)
+        .ok_or(FilesError::InvalidUrl)?;
+
+    let res = moodle.download(&url, headers.get(header::RANGE)).await?;
+
+    let status = res.status();
+    if !status.is_success() && status != StatusCode::RANGE_NOT_SATISFIABLE {
+        return Err(FilesError::Upstream(status));
+    }
+
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .header(header::CONTENT_SECURITY_POLICY, "sandbox")
+        .header(
+            header::CONTENT_DISPOSITION,
+            content_disposition(
+                res.headers().get(header::CONTENT_TYPE),
+                res.headers().get(header::CONTENT_DISPOSITION),
+            ),
+        );
+    for name in PASSTHROUGH {
+        if let Some(value) = res.headers().get(&name) {
+            response = response.header(name, value);
+        }
+    }
+
+    response
+        .body(boxed(StreamBody::new(res.bytes_stream())))
+        .map_err(|e| FilesError::Unexpected(e.into()))
+}
+
+/// Moodle's disposition for types in [`INLINE_TYPES`], otherwise an
+/// attachment, keeping moodle's filename.
+fn content_disposition(
+    content_type: Option<&HeaderValue>,
+    disposition: Option<&HeaderValue>,
+) -> HeaderValue {
+    let essence = content_type
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|essence| essence.trim().to_ascii_lowercase());
+    let inline = essence.is_some_and(|essence| INLINE_TYPES.contains(&essence.as_str()));
+
+    match disposition {
+        Some(disposition) if inline => disposition.clone(),
+        None if inline => HeaderValue::from_static("inline"),
+        _ => {
+            let filename = disposition
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split_once(';'))
+                .map(|(_, params)| params);
+            match filename {
+                Some(params) => HeaderValue::from_str(&format!("attachment;{params}"))
+                    .unwrap_or(HeaderValue::from_static("attachment")),
+                None => HeaderValue::from_static("attachment"),
+            }
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum FilesError {
+    #[error("url is not a file of the configured moodle")]
+    InvalidUrl,
+    #[error("error downloading file from moodle")]
+    Moodle(#[from] MoodleError),
+    #[error("moodle responded with status {0}")]
+    Upstream(StatusCode),
+    #[error("unexpected error")]
+    Unexpected(#[source] eyre::Error),
+}
+
+impl IntoResponse for FilesError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            FilesError::InvalidUrl => StatusCode::BAD_REQUEST,
+            FilesError::Moodle(e) => e.status(),
+            FilesError::Upstream(status @ (StatusCode::FORBIDDEN | StatusCode::NOT_FOUND)) => {
+                *status
+            }
+            FilesError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            FilesError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let service = match &self {
+            FilesError::InvalidUrl | FilesError::Unexpected(_) => "mita",
+            FilesError::Moodle(_) | FilesError::Upstream(_) => "moodle",
+        };
+        tracing::error!(%service, %status, error = ?self);
+        status.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::content_disposition;
+
+    fn disposition(content_type: &str, disposition: Option<&'static str>) -> HeaderValue {
+        content_disposition(
+            Some(&HeaderValue::from_str(content_type).unwrap()),
+            disposition.map(HeaderValue::from_static).as_ref(),
+        )
+    }
+
+    #[test]
+    fn only_images_and_pdfs_are_inline() {
+        assert_eq!(
+            disposition("image/PNG", Some(r#"inline; filename="a.png""#)),
+            r#"inline; filename="a.png""#
+        );
+        assert_eq!(disposition("application/pdf", None), "inline");
+        assert_eq!(
+            disposition(
+                "text/html; charset=utf-8",
+                Some(r#"inline; filename="a.html""#)
+            ),
+            r#"attachment; filename="a.html""#
+        );
+        assert_eq!(disposition("image/svg+xml", None), "attachment");
+        assert_eq!(
+            content_disposition(None, Some(&HeaderValue::from_static("inline"))),
+            "attachment"
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use axum::{
+    extract::{Path, Query},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    html,
+    moodle::{
+        self,
+        error::MoodleError,
+        forum::{
+            Discussion, Forum, GetDiscussionPosts, GetDiscussionPostsParams, GetForumDiscussions,
+            GetForumDiscussionsParams, GetForumsByCourses, GetForumsByCoursesParams, Post,
+            PostAttachment, SORT_DEFAULT,
+        },
+    },
+};
+
+const MAX_PER_PAGE: u32 = 100;
+const DEFAULT_PER_PAGE: u32 = 20;
+
+#[derive(Debug, Serialize)]
+pub struct ForumResponse {
+    pub id: u64,
+    pub cmid: u64,
+    pub course_id: u64,
+    pub name: String,
+    /// `news` for the course's announcements, `general`, `qanda`, ...
+    pub forum_type: String,
+    /// Plain text.
+    pub description: Option<String>,
+    pub discussion_count: Option<u32>,
+    pub unread_count: Option<u32>,
+}
+
+impl From<Forum> for ForumResponse {
+    fn from(forum: Forum) -> Self {
+        Self {
+            id: forum.id,
+            cmid: forum.cmid,
+            course_id: forum.course,
+            name: forum.name,
+            forum_type: forum.kind,
+            description: Some(html::to_text(&forum.intro)).filter(|d| !d.is_empty()),
+            discussion_count: forum.numdiscussions,
+            unread_count: forum.unreadpostscount,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DiscussionsQuery {
+    /// From 0.
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiscussionResponse {
+    pub id: u64,
+    pub subject: String,
+    /// Plain text of the first post.
+    pub message: String,
+    pub author: Option<String>,
+    pub created_at: i64,
+    /// Time of the latest post.
+    pub last_post_at: i64,
+    pub pinned: bool,
+    pub locked: bool,
+    pub reply_count: u32,
+    pub unread_count: u32,
+}
+
+impl From<Discussion> for DiscussionResponse {
+    fn from(discussion: Discussion) -> Self {
+        Self {
+            id: discussion.discussion,
+            subject: discussion.subject,
+            message: html::to_text(&discussion.message),
+            author: discussion.userfullname,
+            created_at: discussion.created,
+            last_post_at: discussion.timemodified,
+            pinned: discussion.pinned,
+            locked: discussion.locked,
+            reply_count: discussion.numreplies,
+            unread_count: discussion.numunread,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PostResponse {
+    pub id: u64,
+    /// `None` for the first post of the discussion.
+    pub parent_id: Option<u64>,
+    pub subject: String,
+    /// Plain text.
+    pub message: String,
+    pub author: Option<String>,
+    pub created_at: i64,
+    pub attachments: Vec<AttachmentResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttachmentResponse {
+    pub filename: String,
+    /// Moodle pluginfile url, download it through `/files`.
+    pub url: String,
+    pub size: u64,
+    pub mime_type: Option<String>,
+}
+
+impl From<Post> for PostResponse {
+    fn from(post: Post) -> Self {
+        Self {
+            id: post.id,
+            parent_id: post.parentid.filter(|&id| id != 0),
+            subject: post.subject,
+            message: html::to_text(&post.message),
+            author: post.author.fullname,
+            created_at: post.timecreated,
+            attachments: post
+                .attachments
+                .into_iter()
+                .map(AttachmentResponse::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<PostAttachment> for AttachmentResponse {
+    fn from(attachment: PostAttachment) -> Self {
+        Self {
+            filename: attachment.filename,
+            url: attachment.url,
+            size: attachment.filesize,
+            mime_type: attachment.mimetype,
+        }
+    }
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_course_forums(
+    moodle: Extension<moodle::Client>,
+    Path(course_id): Path<u64>,
+) -> Result<Json<Vec<ForumResponse>>, ForumsError> {
+    let forums = moodle
+        .call::<GetForumsByCourses>(&GetForumsByCoursesParams {
+            courseids: vec![course_id],
+        })
+        .await?;
+
+    Ok(Json(forums.into_iter().map(ForumResponse::from).collect()))
+}
+
+/// Most recently active first, pinned discussions on top.
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_forum_discussions(
+    moodle: Extension<moodle::Client>,
+    Path(forum_id): Path<u64>,
+    query: Query<DiscussionsQuery>,
+) -> Result<Json<Vec<DiscussionResponse>>, ForumsError> {
+    let discussions = forum_discussions(
+        &moodle,
+        forum_id,
+        SORT_DEFAULT,
+        query.page.unwrap_or(0),
+        query.per_page.unwrap_or(DEFAULT_PER_PAGE),
+    )
+    .await?;
+
+    Ok(Json(
+        discussions
+            .into_iter()
+            .map(DiscussionResponse::from)
+            .collect(),
+    ))
+}
+
+pub async fn forum_discussions(
+    moodle: &moodle::Client,
+    forum_id: u64,
+    sortorder: i32,
+    page: u32,
+    per_page: u32,
+) -> Result<Vec<Discussion>, MoodleError> {
+    let res = moodle
+        .call::<GetForumDiscussions>(&GetForumDiscussionsParams {
+            forumid: forum_id,
+            sortorder,
+            page,
+            perpage: per_page.clamp(1, MAX_PER_PAGE),
+        })
+        .await?;
+
+    Ok(res.discussions)
+}
+
+/// Oldest first, so replies follow what they reply to.
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_discussion_posts(
+    moodle: Extension<moodle::Client>,
+    Path(discussion_id): Path<u64>,
+) -> Result<Json<Vec<PostResponse>>, ForumsError> {
+    let res = moodle
+        .call::<GetDiscussionPosts>(&GetDiscussionPostsParams {
+            discussionid: discussion_id,
+            sortby: "created",
+            sortdirection: "ASC",
+        })
+        .await?;
+
+    Ok(Json(
+        res.posts
+            .into_iter()
+            .filter(|post| !post.isdeleted)
+            .map(PostResponse::from)
+            .collect(),
+    ))
+}
+
+#[derive(Error, Debug)]
+pub enum ForumsError {
+    #[error("error from moodle")]
+    Moodle(#[from] MoodleError),
+}
+
+impl IntoResponse for ForumsError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            ForumsError::Moodle(e) => e.status(),
+        };
+        tracing::error!(service = "moodle", %status, error = ?self);
+        status.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn first_post_has_no_parent() {
+        let post: Post = serde_json::from_value(json!({
+            "id": 10,
+            "discussionid": 4,
+            "parentid": 0,
+            "subject": "Room change",
+            "message": "<p>Lab moved to <b>H6-501</b></p>",
+            "author": {},
+            "timecreated": 100,
+            "attachments": [{
+                "filename": "map.png",
+                "url": "https://e-learning.hcmut.edu.vn/webservice/pluginfile.php/1/mod_forum/attachment/10/map.png",
+                "filesize": 20,
+                "mimetype": "image/png",
+            }],
+        }))
+        .unwrap();
+        let res = PostResponse::from(post);
+        assert_eq!(res.parent_id, None);
+        assert_eq!(res.author, None);
+        assert_eq!(res.message, "Lab moved to H6-501");
+        assert_eq!(res.attachments[0].filename, "map.png");
+    }
+}
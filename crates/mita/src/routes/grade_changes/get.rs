@@ -0,0 +1,53 @@
+use axum::{
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    app_state::AppState,
+    grade_watch::{self, GradeChange},
+    oidc::Claims,
+};
+
+const LIMIT: u32 = 200;
+
+#[derive(Debug, Deserialize)]
+pub struct ChangesQuery {
+    /// Unix timestamp, defaults to the beginning of time.
+    #[serde(default)]
+    pub since: i64,
+    /// Id of the last change seen at `since`, to get the next page send the
+    /// last change's `detected_at` and `id`.
+    #[serde(default)]
+    pub after: i64,
+}
+
+/// Grade changes found by the grade watcher, oldest first, at most
+/// [`LIMIT`] at once.
+#[axum::debug_handler(state = AppState)]
+#[tracing::instrument(skip(claims, state))]
+pub async fn get_grade_changes(
+    claims: Extension<Claims>,
+    state: State<AppState>,
+    query: Query<ChangesQuery>,
+) -> Result<Json<Vec<GradeChange>>, GradeChangesError> {
+    Ok(Json(
+        grade_watch::changes_since(&state.pool, &claims.sub, query.since, query.after, LIMIT)
+            .await?,
+    ))
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct GradeChangesError(#[from] sqlx::Error);
+
+impl IntoResponse for GradeChangesError {
+    fn into_response(self) -> Response {
+        let status = reqwest::StatusCode::INTERNAL_SERVER_ERROR;
+        tracing::error!(service = "mita", %status, error = ?self, "error getting grade changes");
+        status.into_response()
+    }
+}
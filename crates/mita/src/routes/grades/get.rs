@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::Path,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    html,
+    moodle::{
+        self,
+        error::MoodleError,
+        grades::{
+            CourseGrade, GetCourseGrades, GetCourseGradesParams, GetGradeItems,
+            GetGradeItemsParams, GradeItem,
+        },
+    },
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GradeItemResponse {
+    pub id: u64,
+    pub name: String,
+    /// `course`, `category`, `mod` or `manual`.
+    pub item_type: String,
+    /// Activity type for `mod` items, e.g. `assign` or `quiz`.
+    pub module: Option<String>,
+    pub cmid: Option<u64>,
+    pub grade: Option<f64>,
+    /// As shown on moodle, which may be a letter or a scale item.
+    pub grade_formatted: Option<String>,
+    pub range: Option<GradeRange>,
+    /// Grade within the range, from 0 to 100.
+    pub percentage: Option<f64>,
+    /// Weight in the parent category, from 0 to 100.
+    pub weight: Option<f64>,
+    /// Plain text, moodle's HTML is stripped.
+    pub feedback: Option<String>,
+    pub graded_at: Option<i64>,
+    pub hidden: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct GradeRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl From<GradeItem> for GradeItemResponse {
+    fn from(item: GradeItem) -> Self {
+        let range = match (item.grademin, item.grademax) {
+            (Some(min), Some(max)) => Some(GradeRange { min, max }),
+            _ => None,
+        };
+        let percentage = match (item.graderaw, range) {
+            (Some(grade), Some(GradeRange { min, max })) if max > min => {
+                Some(round((grade - min) / (max - min) * 100.0))
+            }
+            _ => None,
+        };
+        let name = item.itemname.unwrap_or_else(|| {
+            match item.itemtype.as_str() {
+                "course" => "Course total",
+                "category" => "Category total",
+                _ => "",
+            }
+            .into()
+        });
+
+        Self {
+            id: item.id,
+            name,
+            item_type: item.itemtype,
+            module: item.itemmodule,
+            cmid: item.cmid,
+            grade: item.graderaw,
+            // moodle formats missing grades as `-`
+            grade_formatted: item.gradeformatted.filter(|g| !g.is_empty() && g != "-"),
+            range,
+            percentage,
+            weight: item.weightraw.map(|w| round(w * 100.0)),
+            feedback: item
+                .feedback
+                .map(|f| html::to_text(&f))
+                .filter(|f| !f.is_empty()),
+            graded_at: item.gradedategraded.filter(|&t| t != 0),
+            hidden: item.gradeishidden,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CourseGradesResponse {
+    pub course_id: u64,
+    pub items: Vec<GradeItemResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GradeSummary {
+    pub course_id: u64,
+    pub course_name: Option<String>,
+    pub grade: Option<f64>,
+    pub grade_formatted: Option<String>,
+    pub rank: Option<u32>,
+}
+
+impl GradeSummary {
+    fn new(grade: CourseGrade, course_name: Option<String>) -> Self {
+        Self {
+            course_id: grade.courseid,
+            course_name,
+            grade: grade.rawgrade.and_then(|g| g.trim().parse().ok()),
+            grade_formatted: Some(grade.grade).filter(|g| !g.is_empty() && g != "-"),
+            rank: grade.rank,
+        }
+    }
+}
+
+/// Every grade item of one course, including category and course totals.
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_course_grades(
+    moodle: Extension<moodle::Client>,
+    Path(course_id): Path<u64>,
+) -> Result<Json<CourseGradesResponse>, GradesError> {
+    Ok(Json(CourseGradesResponse {
+        course_id,
+        items: course_grade_items(&moodle, course_id).await?,
+    }))
+}
+
+/// The course total of every enrolled course.
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_grades(
+    moodle: Extension<moodle::Client>,
+) -> Result<Json<Vec<GradeSummary>>, GradesError> {
+    let params = GetCourseGradesParams {
+        userid: moodle.user_id(),
+    };
+    let (grades, courses) = futures::try_join!(
+        moodle.call::<GetCourseGrades>(&params),
+        moodle.get_courses(),
+    )?;
+
+    let mut course_names = courses
+        .into_iter()
+        .map(|c| (c.id, c.fullname))
+        .collect::<HashMap<_, _>>();
+
+    Ok(Json(
+        grades
+            .grades
+            .into_iter()
+            .map(|g| {
+                let name = course_names.remove(&g.courseid);
+                GradeSummary::new(g, name)
+            })
+            .collect(),
+    ))
+}
+
+pub async fn course_grade_items(
+    moodle: &moodle::Client,
+    course_id: u64,
+) -> Result<Vec<GradeItemResponse>, MoodleError> {
+    let res = moodle
+        .call::<GetGradeItems>(&GetGradeItemsParams {
+            courseid: course_id,
+            userid: moodle.user_id(),
+        })
+        .await?;
+
+    Ok(res
+        .usergrades
+        .into_iter()
+        .flat_map(|g| g.gradeitems)
+        .map(GradeItemResponse::from)
+        .collect())
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[derive(Error, Debug)]
+pub enum GradesError {
+    #[error("error getting grades from moodle")]
+    Moodle(#[from] MoodleError),
+}
+
+impl IntoResponse for GradesError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            GradesError::Moodle(e) => e.status(),
+        };
+        tracing::error!(service = "moodle", %status, error = ?self);
+        status.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{GradeItemResponse, GradeSummary};
+    use crate::moodle::grades::{CourseGrade, GradeItem};
+
+    #[test]
+    fn normalizes_grade_item() -> eyre::Result<()> {
+        let item: GradeItem = serde_json::from_value(json!({
+            "id": 7,
+            "itemname": "Lab 1",
+            "itemtype": "mod",
+            "itemmodule": "assign",
+            "cmid": 42,
+            "weightraw": 0.125,
+            "graderaw": 7.5,
+            "gradeformatted": "7.50",
+            "gradedategraded": 1678000000,
+            "grademin": 0,
+            "grademax": 10,
+            "feedback": "<p>Good&nbsp;work</p>",
+            "gradeishidden": false,
+        }))?;
+
+        let item = GradeItemResponse::from(item);
+
+        assert_eq!(item.percentage, Some(75.0));
+        assert_eq!(item.weight, Some(12.5));
+        assert_eq!(item.feedback.as_deref(), Some("Good work"));
+        assert_eq!(item.graded_at, Some(1678000000));
+
+        Ok(())
+    }
+
+    #[test]
+    fn ungraded_item() -> eyre::Result<()> {
+        let item: GradeItem = serde_json::from_value(json!({
+            "id": 1,
+            "itemname": null,
+            "itemtype": "course",
+            "itemmodule": null,
+            "graderaw": null,
+            "gradeformatted": "-",
+            "gradedategraded": null,
+            "grademin": 0,
+            "grademax": 100,
+            "feedback": "",
+        }))?;
+
+        let item = GradeItemResponse::from(item);
+
+        assert_eq!(item.name, "Course total");
+        assert_eq!(item.grade, None);
+        assert_eq!(item.grade_formatted, None);
+        assert_eq!(item.percentage, None);
+        assert_eq!(item.feedback, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parses_raw_course_grade() -> eyre::Result<()> {
+        let grade: CourseGrade = serde_json::from_value(json!({
+            "courseid": 3,
+            "grade": "8.50",
+            "rawgrade": "8.50000",
+        }))?;
+
+        let summary = GradeSummary::new(grade, None);
+
+        assert_eq!(summary.grade, Some(8.5));
+        assert_eq!(summary.grade_formatted.as_deref(), Some("8.50"));
+
+        Ok(())
+    }
+}
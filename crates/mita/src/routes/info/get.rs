@@ -2,36 +2,31 @@ use axum::{
     response::{IntoResponse, Response},
     Extension, Json,
 };
-use eyre::Context;
-use reqwest::StatusCode;
 use serde_json::json;
 use thiserror::Error;
 
-use crate::moodle;
+use crate::moodle::{self, error::MoodleError};
 
 #[axum::debug_handler]
 #[tracing::instrument(skip(moodle))]
 pub async fn get_info(moodle: Extension<moodle::Client>) -> Result<Response, InfoError> {
-    // this should always succeed because the middleware should have already
-    // verified the token. if it fails, the moodle server is in a bad state
-    let info = moodle
-        .get_info()
-        .await
-        .wrap_err("error getting info from moodle")?;
+    // the token can still turn out stale here, when the middleware trusted
+    // an earlier validation
+    let info = moodle.fresh_info().await?;
 
     Ok(Json(json!({ "fullname": &info.fullname })).into_response())
 }
 
 #[derive(Error, Debug)]
 pub enum InfoError {
-    #[error("unexpected error")]
-    Unexpected(#[from] eyre::Error),
+    #[error("error getting info from moodle")]
+    Moodle(#[from] MoodleError),
 }
 
 impl IntoResponse for InfoError {
     fn into_response(self) -> Response {
         let status = match &self {
-            InfoError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            InfoError::Moodle(e) => e.status(),
         };
         tracing::error!(service = "moodle", %status, error = ?self);
         status.into_response()
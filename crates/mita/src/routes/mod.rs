@@ -1,6 +1,25 @@
+pub mod announcements;
+pub mod archive;
+pub mod assignments;
+pub mod calendar;
+pub mod channels;
+pub mod completion;
+pub mod contents;
+pub mod conversations;
+pub mod courses;
+pub mod dashboard;
+pub mod deadlines;
+pub mod feeds;
+pub mod files;
+pub mod forums;
+pub mod grade_changes;
+pub mod grades;
 pub mod info;
+pub mod notifications;
+pub mod quizzes;
 pub mod router;
 pub mod token;
+pub mod users;
 
 // basic handler that responds with a static string
 pub async fn root() -> &'static str {
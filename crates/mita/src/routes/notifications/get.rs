@@ -0,0 +1,153 @@
+use axum::{
+    extract::Query,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    html,
+    moodle::{
+        self,
+        error::MoodleError,
+        message::{GetPopupNotifications, GetPopupNotificationsParams, PopupNotification},
+    },
+};
+
+const MAX_LIMIT: u32 = 50;
+const DEFAULT_LIMIT: u32 = 20;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct NotificationsQuery {
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationsResponse {
+    pub unread_count: u32,
+    /// Newest first.
+    pub notifications: Vec<NotificationResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationResponse {
+    pub id: u64,
+    pub subject: String,
+    /// Plain text.
+    pub text: String,
+    /// The moodle page the notification is about.
+    pub url: Option<String>,
+    pub url_name: Option<String>,
+    /// Moodle component that sent the notification, e.g. `mod_forum`.
+    pub component: Option<String>,
+    pub event_type: Option<String>,
+    pub created_at: i64,
+    pub read_at: Option<i64>,
+    pub read: bool,
+}
+
+impl From<PopupNotification> for NotificationResponse {
+    fn from(notification: PopupNotification) -> Self {
+        let text = notification
+            .fullmessagehtml
+            .as_deref()
+            .or(notification.smallmessage.as_deref())
+            .map(html::to_text)
+            .unwrap_or_default();
+        Self {
+            id: notification.id,
+            subject: notification.subject,
+            text,
+            url: notification.contexturl,
+            url_name: notification.contexturlname,
+            component: notification.component,
+            event_type: notification.eventtype,
+            created_at: notification.timecreated,
+            read_at: notification.timeread,
+            read: notification.read || notification.timeread.is_some(),
+        }
+    }
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_notifications(
+    moodle: Extension<moodle::Client>,
+    query: Query<NotificationsQuery>,
+) -> Result<Json<NotificationsResponse>, NotificationsError> {
+    Ok(Json(notifications(&moodle, &query).await?))
+}
+
+pub async fn notifications(
+    moodle: &moodle::Client,
+    query: &NotificationsQuery,
+) -> Result<NotificationsResponse, MoodleError> {
+    let res = moodle
+        .call::<GetPopupNotifications>(&GetPopupNotificationsParams {
+            useridto: moodle.user_id(),
+            newestfirst: true,
+            limit: query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT),
+            offset: query.offset.unwrap_or(0),
+        })
+        .await?;
+
+    Ok(NotificationsResponse {
+        unread_count: res.unreadcount,
+        notifications: res
+            .notifications
+            .into_iter()
+            .map(NotificationResponse::from)
+            .collect(),
+    })
+}
+
+#[derive(Error, Debug)]
+pub enum NotificationsError {
+    #[error("at most {0} notifications can be marked read at once")]
+    TooMany(usize),
+    #[error("error from moodle")]
+    Moodle(#[from] MoodleError),
+}
+
+impl IntoResponse for NotificationsError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            NotificationsError::TooMany(_) => StatusCode::BAD_REQUEST,
+            NotificationsError::Moodle(e) => e.status(),
+        };
+        let service = match &self {
+            NotificationsError::TooMany(_) => "mita",
+            NotificationsError::Moodle(_) => "moodle",
+        };
+        tracing::error!(%service, %status, error = ?self);
+        status.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn prefers_full_message() {
+        let notification: PopupNotification = serde_json::from_value(json!({
+            "id": 1,
+            "useridfrom": -10,
+            "subject": "New forum post",
+            "fullmessagehtml": "<p>Lab 2 is <b>cancelled</b></p>",
+            "smallmessage": "Lab 2",
+            "timecreated": 100,
+            "timeread": null,
+            "read": false,
+        }))
+        .unwrap();
+        let res = NotificationResponse::from(notification);
+        assert_eq!(res.text, "Lab 2 is cancelled");
+        assert!(!res.read);
+    }
+}
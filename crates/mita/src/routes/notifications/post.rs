@@ -0,0 +1,70 @@
+use axum::{http::StatusCode, Extension, Json};
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+
+use super::get::NotificationsError;
+use crate::{
+    clock::now,
+    moodle::{
+        self,
+        error::{MoodleError, MoodleWarning},
+        message::{
+            MarkAllNotificationsRead, MarkAllNotificationsReadParams, MarkNotificationRead,
+            MarkNotificationReadParams,
+        },
+    },
+};
+
+/// Notifications one request can mark read.
+const MAX_IDS: usize = 100;
+/// Notifications marked read at once.
+const CONCURRENCY: usize = 4;
+
+#[derive(Debug, Deserialize)]
+pub struct ReadNotifications {
+    /// Every notification of the user when missing.
+    pub ids: Option<Vec<u64>>,
+}
+
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn read_notifications(
+    moodle: Extension<moodle::Client>,
+    Json(body): Json<ReadNotifications>,
+) -> Result<StatusCode, NotificationsError> {
+    let Some(ids) = body.ids else {
+        moodle
+            .call::<MarkAllNotificationsRead>(&MarkAllNotificationsReadParams {
+                useridto: moodle.user_id(),
+            })
+            .await?;
+        moodle.invalidate_cache();
+        return Ok(StatusCode::NO_CONTENT);
+    };
+
+    if ids.len() > MAX_IDS {
+        return Err(NotificationsError::TooMany(MAX_IDS));
+    }
+
+    let timeread = now();
+    let moodle = &*moodle;
+    let responses: Vec<_> = stream::iter(ids)
+        .map(|notificationid| async move {
+            moodle
+                .call::<MarkNotificationRead>(&MarkNotificationReadParams {
+                    notificationid,
+                    timeread,
+                })
+                .await
+        })
+        .buffer_unordered(CONCURRENCY)
+        .collect()
+        .await;
+    // some may have been marked even if others failed
+    moodle.invalidate_cache();
+    for res in responses {
+        MoodleWarning::check(res?.warnings).map_err(MoodleError::from)?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::Query,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    clock::now,
+    moodle::{
+        self,
+        error::MoodleError,
+        quiz::{
+            BestGrade, GetQuizzesByCourses, GetQuizzesByCoursesParams, GetUserAttempts,
+            GetUserAttemptsParams, GetUserBestGrade, GetUserBestGradeParams, Quiz, QuizAttempt,
+        },
+    },
+};
+
+/// Quizzes whose attempts are fetched at once.
+const CONCURRENCY: usize = 4;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct QuizzesQuery {
+    /// Only quizzes in this state.
+    pub status: Option<QuizStatus>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuizStatus {
+    Open,
+    Upcoming,
+    Closed,
+}
+
+impl QuizStatus {
+    fn new(opens_at: Option<i64>, closes_at: Option<i64>, now: i64) -> Self {
+        match (opens_at, closes_at) {
+            (Some(open), _) if now < open => QuizStatus::Upcoming,
+            (_, Some(close)) if now >= close => QuizStatus::Closed,
+            _ => QuizStatus::Open,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuizResponse {
+    pub id: u64,
+    pub cmid: u64,
+    pub course_id: u64,
+    pub course_name: String,
+    pub name: String,
+    pub status: QuizStatus,
+    pub opens_at: Option<i64>,
+    pub closes_at: Option<i64>,
+    /// Seconds.
+    pub time_limit: Option<u64>,
+    /// `None` for unlimited.
+    pub attempts_allowed: Option<u32>,
+    pub attempts_remaining: Option<u32>,
+    pub attempts: Vec<AttemptResponse>,
+    /// Out of `max_grade`.
+    pub best_grade: Option<f64>,
+    pub max_grade: Option<f64>,
+    pub grade_to_pass: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttemptResponse {
+    pub number: u32,
+    /// `inprogress`, `overdue`, `finished` or `abandoned`.
+    pub state: String,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+    /// Out of the quiz's `max_grade`, `None` until graded.
+    pub grade: Option<f64>,
+}
+
+impl AttemptResponse {
+    fn new(attempt: QuizAttempt, quiz: &Quiz) -> Self {
+        // attempts are marked out of the question total, scale them like
+        // moodle shows them
+        let grade = match (attempt.sumgrades, quiz.sumgrades, quiz.grade) {
+            (Some(marks), Some(total), Some(max)) if total > 0.0 => {
+                Some(round(marks / total * max))
+            }
+            _ => None,
+        };
+        Self {
+            number: attempt.attempt,
+            state: attempt.state,
+            started_at: attempt.timestart,
+            finished_at: (attempt.timefinish != 0).then_some(attempt.timefinish),
+            grade,
+        }
+    }
+}
+
+/// Quizzes of every enrolled course, open ones first, then by closing time.
+#[axum::debug_handler]
+#[tracing::instrument(skip(moodle))]
+pub async fn get_quizzes(
+    moodle: Extension<moodle::Client>,
+    query: Query<QuizzesQuery>,
+) -> Result<Json<Vec<QuizResponse>>, QuizzesError> {
+    let courses = moodle.get_courses().await?;
+    if courses.is_empty() {
+        return Ok(Json(vec![]));
+    }
+    let course_names: HashMap<_, _> = courses.iter().map(|c| (c.id, c.fullname.clone())).collect();
+
+    let now = now();
+    let quizzes = moodle
+        .call::<GetQuizzesByCourses>(&GetQuizzesByCoursesParams {
+            courseids: courses.iter().map(|c| c.id).collect(),
+        })
+        .await?
+        .quizzes
+        .into_iter()
+        .map(|quiz| {
+            let opens_at = (quiz.timeopen != 0).then_some(quiz.timeopen);
+            let closes_at = (quiz.timeclose != 0).then_some(quiz.timeclose);
+            (quiz, QuizStatus::new(opens_at, closes_at, now))
+        })
+        .filter(|(_, status)| query.status.is_none_or(|s| s == *status));
+
+    let moodle = &*moodle;
+    let course_names = &course_names;
+    let mut quizzes: Vec<_> = stream::iter(quizzes)
+        .map(|(quiz, status)| async move {
+            let (attempts, best_grade) = attempts_and_best_grade(moodle, &quiz).await?;
+
+            let attempts: Vec<_> = attempts
+                .into_iter()
+                .map(|a| AttemptResponse::new(a, &quiz))
+                .collect();
+            let attempts_allowed = (quiz.attempts != 0).then_some(quiz.attempts);
+            Ok::<_, MoodleError>(QuizResponse {
+                id: quiz.id,
+                cmid: quiz.coursemodule,
+                course_id: quiz.course,
+                course_name: course_names.get(&quiz.course).cloned().unwrap_or_default(),
+                name: quiz.name,
+                status,
+                opens_at: (quiz.timeopen != 0).then_some(quiz.timeopen),
+                closes_at: (quiz.timeclose != 0).then_some(quiz.timeclose),
+                time_limit: (quiz.timelimit != 0).then_some(quiz.timelimit),
+                attempts_allowed,
+                attempts_remaining: attempts_allowed
+                    .map(|allowed| allowed.saturating_sub(attempts.len() as u32)),
+                attempts,
+                best_grade: best_grade
+                    .as_ref()
+                    .filter(|best_grade| best_grade.hasgrade)
+                    .and_then(|best_grade| best_grade.grade)
+                    .map(round),
+                max_grade: quiz.grade,
+                grade_to_pass: best_grade
+                    .and_then(|best_grade| best_grade.gradetopass)
+                    .filter(|&g| g > 0.0),
+            })
+        })
+        .buffered(CONCURRENCY)
+        .try_collect()
+        .await?;
+
+    quizzes.sort_by_key(|q| (q.status, q.closes_at.is_none(), q.closes_at));
+
+    Ok(Json(quizzes))
+}
+
+/// The user's attempts at a quiz and their best grade. Quizzes behind access
+/// restrictions have neither rather than failing the whole list, other
+/// errors, e.g. a rejected token or maintenance, still fail it.
+async fn attempts_and_best_grade(
+    moodle: &moodle::Client,
+    quiz: &Quiz,
+) -> Result<(Vec<QuizAttempt>, Option<BestGrade>), MoodleError> {
+    if !quiz.has_settings() {
+        return Ok((vec![], None));
+    }
+
+    let attempts_params = GetUserAttemptsParams {
+        quizid: quiz.id,
+        userid: moodle.user_id(),
+        status: "all",
+        includepreviews: false,
+    };
+    let best_grade_params = GetUserBestGradeParams {
+        quizid: quiz.id,
+        userid: moodle.user_id(),
+    };
+    let res = futures::try_join!(
+        moodle.call::<GetUserAttempts>(&attempts_params),
+        moodle.call::<GetUserBestGrade>(&best_grade_params),
+    );
+
+    match res {
+        Ok((attempts, best_grade)) => Ok((attempts.attempts, Some(best_grade))),
+        Err(MoodleError::Api(error)) if error.kind.is_access_restriction() => {
+            tracing::debug!(quiz = quiz.id, ?error, "cannot read attempts of quiz");
+            Ok((vec![], None))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[derive(Error, Debug)]
+pub enum QuizzesError {
+    #[error("error from moodle")]
+    Moodle(#[from] MoodleError),
+}
+
+impl IntoResponse for QuizzesError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            QuizzesError::Moodle(e) => e.status(),
+        };
+        tracing::error!(service = "moodle", %status, error = ?self);
+        status.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn status_from_window() {
+        assert_eq!(QuizStatus::new(None, None, 100), QuizStatus::Open);
+        assert_eq!(QuizStatus::new(Some(200), None, 100), QuizStatus::Upcoming);
+        assert_eq!(
+            QuizStatus::new(Some(50), Some(100), 100),
+            QuizStatus::Closed
+        );
+        assert_eq!(QuizStatus::new(Some(50), Some(150), 100), QuizStatus::Open);
+    }
+
+    #[test]
+    fn attempt_grade_is_scaled_to_quiz_grade() {
+        let quiz: Quiz = serde_json::from_value(json!({
+            "id": 1,
+            "course": 2,
+            "coursemodule": 3,
+            "name": "Quiz 1",
+            "grade": 10.0,
+            "sumgrades": 30.0,
+        }))
+        .unwrap();
+        let attempt: QuizAttempt = serde_json::from_value(json!({
+            "id": 7,
+            "attempt": 1,
+            "state": "finished",
+            "timestart": 100,
+            "timefinish": 200,
+            "sumgrades": 20.0,
+        }))
+        .unwrap();
+
+        assert_eq!(AttemptResponse::new(attempt, &quiz).grade, Some(6.67));
+    }
+}
@@ -1,10 +1,43 @@
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
-    routing::{get, put},
+    routing::{delete, get, post},
     Router,
 };
 
-use super::{info::get::get_info, root, token::put::register_token};
+use super::{
+    announcements::get::get_announcements,
+    archive::get::get_course_archive,
+    assignments::{
+        get::get_assignment_status,
+        post::{submit_assignment, MAX_SUBMISSION_BODY},
+    },
+    calendar::{delete::revoke_calendar_feed, get::get_calendar_feed, post::create_calendar_feed},
+    channels::{delete::remove_channel, get::get_channels, post::add_channel},
+    completion::{get::get_course_completion, post::set_activity_completion},
+    contents::get::get_course_contents,
+    conversations::{
+        get::{get_conversation_messages, get_conversations},
+        post::send_conversation_message,
+    },
+    courses::get::get_courses,
+    dashboard::get::get_dashboard,
+    deadlines::get::get_deadlines,
+    feeds::{
+        delete::revoke_announcements_feed, get::get_announcements_feed,
+        post::create_announcements_feed,
+    },
+    files::get::get_file,
+    forums::get::{get_course_forums, get_discussion_posts, get_forum_discussions},
+    grade_changes::get::get_grade_changes,
+    grades::get::{get_course_grades, get_grades},
+    info::get::get_info,
+    notifications::{get::get_notifications, post::read_notifications},
+    quizzes::get::get_quizzes,
+    root,
+    token::{delete::delete_token, get::get_token_status, put::register_token},
+    users::post::send_user_message,
+};
 use crate::{
     app_state::AppState,
     middlewares::{moodle::build_moodle_client, vault::authenticate},
@@ -14,20 +47,79 @@ use crate::{
 pub fn app_router(state: AppState) -> Router<()> {
     Router::new()
         .route("/", get(root))
+        // calendar apps and feed readers can't authenticate, the feed token in the path is
+        // the credential
+        .route("/calendar/:feed", get(get_calendar_feed))
+        .route(
+            "/feeds/:feed_token/announcements.atom",
+            get(get_announcements_feed),
+        )
         .merge(protected_router(state.clone()))
         .with_state(state)
         .layer(router_telemetry_layer())
 }
 
 fn protected_router(state: AppState) -> Router<AppState> {
-    Router::new()
-        .route("/token", put(register_token))
+    let router = Router::new()
+        .route(
+            "/token",
+            get(get_token_status)
+                .put(register_token)
+                .delete(delete_token),
+        )
+        .route("/calendar/feed", delete(revoke_calendar_feed))
+        .route("/feeds/announcements", delete(revoke_announcements_feed));
+
+    // grades are only watched when mita can read tokens on its own
+    let router = if state.secret_store.readable_by_mita() {
+        router
+            .route("/grades/changes", get(get_grade_changes))
+            .route("/channels", get(get_channels).post(add_channel))
+            .route("/channels/:id", delete(remove_channel))
+    } else {
+        router
+    };
+
+    router
         .merge(registered_router(state.clone()))
-        .layer(middleware::from_fn_with_state(state, authenticate))
+        .route_layer(middleware::from_fn_with_state(state, authenticate))
 }
 
 fn registered_router(state: AppState) -> Router<AppState> {
     Router::new()
         .route("/info", get(get_info))
-        .layer(middleware::from_fn_with_state(state, build_moodle_client))
+        .route("/courses", get(get_courses))
+        .route("/courses/:id/archive.zip", get(get_course_archive))
+        .route("/courses/:id/completion", get(get_course_completion))
+        .route("/courses/:id/contents", get(get_course_contents))
+        .route("/courses/:id/forums", get(get_course_forums))
+        .route("/courses/:id/grades", get(get_course_grades))
+        .route("/grades", get(get_grades))
+        .route("/dashboard", get(get_dashboard))
+        .route("/deadlines", get(get_deadlines))
+        .route("/quizzes", get(get_quizzes))
+        .route("/files", get(get_file))
+        .route(
+            "/activities/:cmid/completion",
+            post(set_activity_completion),
+        )
+        .route("/assignments/:cmid/status", get(get_assignment_status))
+        .route(
+            "/assignments/:cmid/submission",
+            post(submit_assignment).layer(DefaultBodyLimit::max(MAX_SUBMISSION_BODY)),
+        )
+        .route("/forums/:id/discussions", get(get_forum_discussions))
+        .route("/discussions/:id/posts", get(get_discussion_posts))
+        .route("/announcements", get(get_announcements))
+        .route("/notifications", get(get_notifications))
+        .route("/notifications/read", post(read_notifications))
+        .route("/conversations", get(get_conversations))
+        .route(
+            "/conversations/:id/messages",
+            get(get_conversation_messages).post(send_conversation_message),
+        )
+        .route("/users/:id/messages", post(send_user_message))
+        .route("/calendar/feed", post(create_calendar_feed))
+        .route("/feeds/announcements", post(create_announcements_feed))
+        .route_layer(middleware::from_fn_with_state(state, build_moodle_client))
 }
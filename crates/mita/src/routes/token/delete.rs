@@ -0,0 +1,57 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::{
+    app_state::AppState,
+    feed_token::{self, FeedTokenError},
+    grade_watch,
+    oidc::Claims,
+    secret_store::{DynSecretStore, SecretStoreError},
+};
+
+#[axum::debug_handler(state = AppState)]
+#[tracing::instrument(skip(secret_store, claims, state))]
+pub async fn delete_token(
+    secret_store: Extension<DynSecretStore>,
+    claims: Extension<Claims>,
+    state: State<AppState>,
+) -> Result<StatusCode, DeleteTokenError> {
+    secret_store.delete_moodle_token().await?;
+
+    // feed tokens carry their own copy of the moodle token
+    feed_token::revoke_all(&state.pool, &claims.sub).await?;
+    grade_watch::forget(&state.pool, &claims.sub).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Error, Debug)]
+pub enum DeleteTokenError {
+    #[error("error deleting moodle token")]
+    DeleteMoodleToken(#[from] SecretStoreError),
+    #[error("error revoking feed tokens")]
+    RevokeFeedToken(#[from] FeedTokenError),
+    #[error("error forgetting grades")]
+    ForgetGrades(#[from] sqlx::Error),
+}
+
+impl IntoResponse for DeleteTokenError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            DeleteTokenError::DeleteMoodleToken(e) => e.status(),
+            DeleteTokenError::RevokeFeedToken(e) => e.status(),
+            DeleteTokenError::ForgetGrades(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let service = match &self {
+            DeleteTokenError::DeleteMoodleToken(_) => "secret_store",
+            DeleteTokenError::RevokeFeedToken(_) | DeleteTokenError::ForgetGrades(_) => "mita",
+        };
+        tracing::error!(%service, %status, error = ?self);
+        status.into_response()
+    }
+}
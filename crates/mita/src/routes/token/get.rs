@@ -0,0 +1,45 @@
+use axum::{
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::secret_store::{DynSecretStore, SecretStoreError};
+
+#[derive(Serialize)]
+pub struct TokenStatus {
+    registered_at: i64,
+    last_validated_at: i64,
+    moodle_username: String,
+    /// Moodle rejected the token, a new one has to be registered.
+    stale: bool,
+}
+
+/// Tells whether the user has a moodle token registered, never the token.
+#[axum::debug_handler]
+#[tracing::instrument(skip(secret_store))]
+pub async fn get_token_status(
+    secret_store: Extension<DynSecretStore>,
+) -> Result<Json<TokenStatus>, TokenStatusError> {
+    let registration = secret_store.get_registration().await?;
+
+    Ok(Json(TokenStatus {
+        registered_at: registration.registered_at,
+        last_validated_at: registration.validated_at,
+        moodle_username: registration.moodle_username,
+        stale: registration.stale,
+    }))
+}
+
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct TokenStatusError(#[from] SecretStoreError);
+
+impl IntoResponse for TokenStatusError {
+    fn into_response(self) -> Response {
+        let status = self.0.status();
+        tracing::error!(service = "secret_store", %status, error = ?self, "error getting moodle token");
+        status.into_response()
+    }
+}
@@ -6,33 +6,87 @@ use thiserror::Error;
 
 use crate::{
     app_state::AppState,
+    clock::now,
+    feed_token::{self, FeedTokenError},
     moodle::{self, error::MoodleError},
-    vault::{self, VaultError},
+    oidc::Claims,
+    registration::Registration,
+    secret_store::{DynSecretStore, SecretStoreError},
 };
 
+/// Either the token from moodle's security keys page, or the moodle
+/// credentials to get one with. The password is never stored.
 #[derive(Deserialize)]
-pub struct FormData {
-    moodle_token: Secret<String>,
+#[serde(untagged)]
+pub enum FormData {
+    Token {
+        moodle_token: Secret<String>,
+    },
+    Credentials {
+        username: String,
+        password: Secret<String>,
+    },
 }
 
-#[axum::debug_handler]
-#[tracing::instrument(skip(vault, state, form))]
+/// Registering a different token revokes the user's calendar and
+/// announcement feeds, which carry their own copy of the old token. They
+/// have to be created again.
+#[axum::debug_handler(state = AppState)]
+#[tracing::instrument(skip(secret_store, claims, state, form))]
 pub async fn register_token(
-    vault: Extension<vault::Client>,
+    secret_store: Extension<DynSecretStore>,
+    claims: Extension<Claims>,
     state: State<AppState>,
-    form: Form<FormData>,
+    Form(form): Form<FormData>,
 ) -> Result<StatusCode, RegisterError> {
-    let moodle_token = form
-        .moodle_token
-        .expose_secret()
-        .parse()
-        .map_err(RegisterError::ValidateToken)?;
+    let moodle_token = match form {
+        FormData::Token { moodle_token } => moodle_token
+            .expose_secret()
+            .parse()
+            .map_err(RegisterError::ValidateToken)?,
+        FormData::Credentials { username, password } => moodle::Client::login(
+            &state.http_client,
+            &state.config.moodle,
+            &username,
+            &password,
+        )
+        .await
+        .map_err(RegisterError::Login)?,
+    };
 
     // verify token by making a request to moodle
-    let moodle =
-        moodle::Client::new(&state.http_client, &state.config.moodle, moodle_token).await?;
+    let moodle = moodle::Client::validate(
+        &state.http_client,
+        &state.config.moodle,
+        moodle_token,
+        // the token may have changed, don't trust what was cached
+        state.moodle_cache.refreshing(),
+        state.moodle_validations.clone(),
+    )
+    .await?;
 
-    vault.put_moodle_token(moodle.token()).await?;
+    let previous = match secret_store.get_moodle_token().await {
+        Ok(previous) => Some(previous),
+        // vault reports a missing secret as its own 404
+        Err(e) if e.status() == StatusCode::NOT_FOUND => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    let now = now();
+    let registration = Registration {
+        moodle_username: moodle.username().into(),
+        registered_at: now,
+        validated_at: now,
+        stale: false,
+    };
+    secret_store
+        .put_moodle_token(moodle.token(), &registration)
+        .await?;
+
+    // e.g. rotated after a leak, feeds mustn't keep using the old token
+    if previous.is_some_and(|previous| previous.expose_secret() != moodle.token().expose_secret()) {
+        feed_token::revoke_all(&state.pool, &claims.sub).await?;
+    }
 
     Ok(StatusCode::OK)
 }
@@ -40,9 +94,13 @@ pub async fn register_token(
 #[derive(Error, Debug)]
 pub enum RegisterError {
     #[error("error putting moodle token")]
-    PutMoodleToken(#[from] VaultError),
+    PutMoodleToken(#[from] SecretStoreError),
+    #[error("error revoking feed tokens")]
+    RevokeFeedTokens(#[from] FeedTokenError),
     #[error("error validating token")]
     ValidateToken(#[source] eyre::Error),
+    #[error("error logging into moodle")]
+    Login(#[source] MoodleError),
     #[error("error verifying token")]
     VerifyToken(#[from] MoodleError),
 }
@@ -51,12 +109,16 @@ impl IntoResponse for RegisterError {
     fn into_response(self) -> Response {
         let status = match &self {
             RegisterError::PutMoodleToken(e) => e.status(),
+            RegisterError::RevokeFeedTokens(e) => e.status(),
             RegisterError::ValidateToken(_) => StatusCode::BAD_REQUEST,
+            RegisterError::Login(e) => e.status(),
             RegisterError::VerifyToken(e) => e.status(),
         };
         let service = match &self {
-            RegisterError::PutMoodleToken(_) => "vault",
+            RegisterError::PutMoodleToken(_) => "secret_store",
+            RegisterError::RevokeFeedTokens(_) => "mita",
             RegisterError::ValidateToken(_) => "mita",
+            RegisterError::Login(_) => "moodle",
             RegisterError::VerifyToken(_) => "moodle",
         };
         tracing::error!(%service, %status, error = ?self);
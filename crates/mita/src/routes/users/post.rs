@@ -0,0 +1,70 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+
+use crate::{
+    app_state::AppState,
+    clock::now,
+    moodle::{
+        self,
+        error::{MoodleApiError, MoodleApiErrorKind, MoodleError},
+        message::{
+            InstantMessage, Message, SendInstantMessages, SendInstantMessagesParams, FORMAT_PLAIN,
+        },
+    },
+    oidc::Claims,
+    routes::conversations::{
+        get::MessageResponse,
+        post::{MessageBody, MessagesError},
+    },
+};
+
+/// Messages a user directly, starting a private conversation if there is
+/// none yet.
+#[axum::debug_handler(state = AppState)]
+#[tracing::instrument(skip(claims, moodle, state, body))]
+pub async fn send_user_message(
+    claims: Extension<Claims>,
+    moodle: Extension<moodle::Client>,
+    state: State<AppState>,
+    Path(user_id): Path<u64>,
+    Json(body): Json<MessageBody>,
+) -> Result<(StatusCode, Json<MessageResponse>), MessagesError> {
+    let text = body.validate(&state, &claims)?;
+
+    let sent = moodle
+        .call::<SendInstantMessages>(&SendInstantMessagesParams {
+            messages: vec![InstantMessage {
+                touserid: user_id,
+                text: text.clone(),
+                textformat: FORMAT_PLAIN,
+            }],
+        })
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| eyre::eyre!("moodle returned no sent message"))
+        .map_err(MoodleError::from)?;
+    moodle.invalidate_cache();
+
+    // moodle only explains failures in a localized message
+    let id = u64::try_from(sent.msgid).map_err(|_| {
+        MoodleError::from(MoodleApiError {
+            kind: MoodleApiErrorKind::UserCantBeMessaged,
+            message: sent.errormessage.clone().unwrap_or_default(),
+        })
+    })?;
+    let message = Message {
+        id,
+        useridfrom: sent.useridfrom.unwrap_or(moodle.user_id()),
+        text: sent.text.unwrap_or(text),
+        timecreated: sent.timecreated.unwrap_or_else(now),
+    };
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MessageResponse::new(message, moodle.user_id())),
+    ))
+}
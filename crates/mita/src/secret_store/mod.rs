@@ -0,0 +1,120 @@
+pub mod sqlite;
+pub mod transit;
+mod users;
+pub mod vault;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::{
+    config::{Config, SecretStoreConfig},
+    moodle::token::MoodleToken,
+    registration::Registration,
+    vault::{transit::Transit, VaultError},
+};
+
+/// Where a user's moodle token is kept. The authenticate middleware opens one
+/// per request for the logged in user.
+#[async_trait]
+pub trait SecretStore: Send + Sync {
+    async fn put_moodle_token(
+        &self,
+        moodle_token: &MoodleToken,
+        registration: &Registration,
+    ) -> Result<(), SecretStoreError>;
+
+    async fn get_moodle_token(&self) -> Result<MoodleToken, SecretStoreError>;
+
+    async fn get_registration(&self) -> Result<Registration, SecretStoreError>;
+
+    /// Records that moodle accepted the token at `validated_at`, which also
+    /// means it's no longer stale.
+    async fn record_validation(&self, validated_at: i64) -> Result<(), SecretStoreError>;
+
+    /// Records that moodle rejected the token.
+    async fn mark_stale(&self) -> Result<(), SecretStoreError>;
+
+    async fn delete_moodle_token(&self) -> Result<(), SecretStoreError>;
+}
+
+pub type DynSecretStore = Arc<dyn SecretStore>;
+
+/// The configured kind of [`SecretStore`].
+#[derive(Clone)]
+pub enum Backend {
+    Vault,
+    Sqlite(sqlite::Database),
+    Transit(transit::Database),
+}
+
+impl Backend {
+    /// Opens the store of any user, without their id token. Only possible
+    /// for stores in mita's own database.
+    pub fn open_stored(&self, subject: &str) -> Option<DynSecretStore> {
+        match self {
+            Backend::Vault => None,
+            Backend::Sqlite(database) => Some(Arc::new(database.open(subject))),
+            Backend::Transit(database) => Some(Arc::new(database.open(subject))),
+        }
+    }
+
+    /// Whether mita can read moodle tokens on its own, without the user's
+    /// id token, which background work such as grade watching needs. Vault
+    /// only hands out a user's secrets to the user.
+    pub fn readable_by_mita(&self) -> bool {
+        !matches!(self, Backend::Vault)
+    }
+
+    /// Subjects of every user with a moodle token in mita's own database.
+    pub async fn stored_subjects(&self) -> Result<Vec<String>, sqlx::Error> {
+        match self {
+            Backend::Vault => Ok(vec![]),
+            Backend::Sqlite(database) => users::subjects(database.pool()).await,
+            Backend::Transit(database) => users::subjects(database.pool()).await,
+        }
+    }
+
+    pub fn new(
+        http_client: &reqwest::Client,
+        config: &'static Config,
+        pool: &sqlx::SqlitePool,
+    ) -> eyre::Result<Self> {
+        Ok(match &config.secret_store {
+            SecretStoreConfig::Vault => Backend::Vault,
+            SecretStoreConfig::Sqlite { key } => {
+                Backend::Sqlite(sqlite::Database::new(pool.clone(), key)?)
+            }
+            SecretStoreConfig::Transit {
+                key_name, token, ..
+            } => Backend::Transit(transit::Database::new(
+                pool.clone(),
+                Transit::new(http_client, &config.vault, key_name, token.clone()),
+            )),
+        })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SecretStoreError {
+    #[error("moodle token not found")]
+    NotFound,
+    #[error("error from vault")]
+    Vault(#[from] VaultError),
+    #[error("database error")]
+    Database(#[from] sqlx::Error),
+    #[error("unexpected error")]
+    Unexpected(#[from] eyre::Error),
+}
+
+impl SecretStoreError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            SecretStoreError::NotFound => StatusCode::NOT_FOUND,
+            SecretStoreError::Vault(e) => e.status(),
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
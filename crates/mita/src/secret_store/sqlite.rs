@@ -0,0 +1,217 @@
+use async_trait::async_trait;
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use eyre::WrapErr;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::SqlitePool;
+
+use super::{users, SecretStore, SecretStoreError};
+use crate::{moodle::token::MoodleToken, registration::Registration};
+
+const NONCE_LEN: usize = 24;
+
+/// Moodle tokens encrypted in mita's own database, for deployments without
+/// vault.
+#[derive(Clone)]
+pub struct Database {
+    pool: SqlitePool,
+    cipher: XChaCha20Poly1305,
+}
+
+impl Database {
+    /// `key` is 64 characters in `a..f` or `0..9`.
+    pub fn new(pool: SqlitePool, key: &Secret<String>) -> eyre::Result<Self> {
+        let key = hex::decode(key.expose_secret()).wrap_err("secret store key is not hex")?;
+        let cipher = XChaCha20Poly1305::new_from_slice(&key)
+            .map_err(|_| eyre::eyre!("secret store key must be 32 bytes"))?;
+        Ok(Self { pool, cipher })
+    }
+
+    pub(super) fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    pub fn open(&self, subject: &str) -> SqliteStore {
+        SqliteStore {
+            database: self.clone(),
+            subject: subject.into(),
+        }
+    }
+}
+
+/// The [`SecretStore`] of a single user, identified by their id token's
+/// subject.
+pub struct SqliteStore {
+    database: Database,
+    subject: String,
+}
+
+impl SqliteStore {
+    fn seal(&self, moodle_token: &MoodleToken) -> eyre::Result<Vec<u8>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        // binding the subject stops rows from being swapped between users
+        let ciphertext = self
+            .database
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: moodle_token.expose_secret().as_bytes(),
+                    aad: self.subject.as_bytes(),
+                },
+            )
+            .map_err(|_| eyre::eyre!("error encrypting moodle token"))?;
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    fn open(&self, sealed: &[u8]) -> eyre::Result<MoodleToken> {
+        if sealed.len() < NONCE_LEN {
+            eyre::bail!("sealed moodle token too short");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self
+            .database
+            .cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: self.subject.as_bytes(),
+                },
+            )
+            .map_err(|_| eyre::eyre!("error decrypting moodle token"))?;
+        String::from_utf8(plaintext)
+            .wrap_err("moodle token is not utf-8")?
+            .parse()
+            .wrap_err("malformed token inside database")
+    }
+}
+
+#[async_trait]
+impl SecretStore for SqliteStore {
+    #[tracing::instrument(skip(self, moodle_token))]
+    async fn put_moodle_token(
+        &self,
+        moodle_token: &MoodleToken,
+        registration: &Registration,
+    ) -> Result<(), SecretStoreError> {
+        let sealed = self.seal(moodle_token)?;
+        users::upsert(&self.database.pool, &self.subject, &sealed, registration).await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_moodle_token(&self) -> Result<MoodleToken, SecretStoreError> {
+        let sealed = users::sealed_token(&self.database.pool, &self.subject)
+            .await?
+            .ok_or(SecretStoreError::NotFound)?;
+
+        Ok(self.open(&sealed)?)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_registration(&self) -> Result<Registration, SecretStoreError> {
+        users::registration(&self.database.pool, &self.subject)
+            .await?
+            .ok_or(SecretStoreError::NotFound)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn record_validation(&self, validated_at: i64) -> Result<(), SecretStoreError> {
+        Ok(users::record_validation(&self.database.pool, &self.subject, validated_at).await?)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn mark_stale(&self) -> Result<(), SecretStoreError> {
+        Ok(users::mark_stale(&self.database.pool, &self.subject).await?)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn delete_moodle_token(&self) -> Result<(), SecretStoreError> {
+        Ok(users::delete(&self.database.pool, &self.subject).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::{ExposeSecret, Secret};
+
+    use super::Database;
+    use crate::{
+        moodle::token::MoodleToken,
+        registration::Registration,
+        secret_store::{SecretStore, SecretStoreError},
+    };
+
+    async fn database() -> eyre::Result<Database> {
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await?;
+        sqlx::migrate!("../../db/migrations").run(&pool).await?;
+        Database::new(pool, &Secret::new("42".repeat(32)))
+    }
+
+    #[tokio::test]
+    async fn put_get_and_delete() -> eyre::Result<()> {
+        let store = database().await?.open("khang");
+        let moodle_token = "ab".repeat(16).parse::<MoodleToken>()?;
+        let registration = Registration {
+            moodle_username: "2012345".into(),
+            registered_at: 1,
+            validated_at: 2,
+            stale: false,
+        };
+
+        store.put_moodle_token(&moodle_token, &registration).await?;
+
+        let stored = store.get_moodle_token().await?;
+        assert_eq!(stored.expose_secret(), moodle_token.expose_secret());
+        assert_eq!(store.get_registration().await?.moodle_username, "2012345");
+
+        store.mark_stale().await?;
+        assert!(store.get_registration().await?.stale);
+        store.record_validation(3).await?;
+        let registration = store.get_registration().await?;
+        assert_eq!(registration.validated_at, 3);
+        assert!(!registration.stale);
+
+        store.delete_moodle_token().await?;
+        claims::assert_matches!(
+            store.get_moodle_token().await,
+            Err(SecretStoreError::NotFound)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn token_is_bound_to_subject() -> eyre::Result<()> {
+        let database = database().await?;
+        let moodle_token = "ab".repeat(16).parse::<MoodleToken>()?;
+        let store = database.open("khang");
+        store
+            .put_moodle_token(&moodle_token, &Registration::default())
+            .await?;
+
+        // pretend the row was moved to another user
+        sqlx::query("UPDATE users SET subject = 'mallory'")
+            .execute(&database.pool)
+            .await?;
+
+        claims::assert_matches!(
+            database.open("mallory").get_moodle_token().await,
+            Err(SecretStoreError::Unexpected(_))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejects_short_key() -> eyre::Result<()> {
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await?;
+        assert!(Database::new(pool, &Secret::new("42".repeat(16))).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use eyre::WrapErr;
+use secrecy::ExposeSecret;
+use sqlx::SqlitePool;
+
+use super::{users, SecretStore, SecretStoreError};
+use crate::{
+    moodle::token::MoodleToken,
+    registration::Registration,
+    vault::transit::{self, Transit},
+};
+
+/// How many ciphertexts are sent to vault in one rewrap request.
+const REWRAP_BATCH: usize = 100;
+
+/// Moodle tokens encrypted by vault's transit engine, with only the
+/// ciphertext kept in mita's own database.
+#[derive(Clone)]
+pub struct Database {
+    pool: SqlitePool,
+    transit: Transit,
+}
+
+impl Database {
+    pub fn new(pool: SqlitePool, transit: Transit) -> Self {
+        Self { pool, transit }
+    }
+
+    pub(super) fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    pub fn open(&self, subject: &str) -> TransitStore {
+        TransitStore {
+            database: self.clone(),
+            subject: subject.into(),
+        }
+    }
+
+    /// Moves every stored token to the latest version of the transit key, so
+    /// older versions can be retired after a rotation. Returns how many
+    /// tokens were rewrapped.
+    #[tracing::instrument(skip(self))]
+    pub async fn rewrap(&self) -> Result<usize, SecretStoreError> {
+        let latest_version = self.transit.latest_version().await?;
+
+        let rows: Vec<(String, Vec<u8>)> = sqlx::query_as("SELECT subject, token FROM users")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let outdated: Vec<(String, String)> = rows
+            .into_iter()
+            .filter_map(|(subject, token)| Some((String::from_utf8(token).ok()?, subject)))
+            .filter(|(ciphertext, _)| {
+                transit::key_version(ciphertext).is_some_and(|v| v < latest_version)
+            })
+            .collect();
+
+        let mut rewrapped = 0;
+        for batch in outdated.chunks(REWRAP_BATCH) {
+            let results = self.transit.rewrap(batch).await?;
+
+            for ((old, subject), new) in batch.iter().zip(results) {
+                let Some(new) = new else { continue };
+                // a token registered meanwhile is already on the latest key
+                let res = sqlx::query("UPDATE users SET token = ? WHERE subject = ? AND token = ?")
+                    .bind(new.as_bytes())
+                    .bind(subject)
+                    .bind(old.as_bytes())
+                    .execute(&self.pool)
+                    .await?;
+                rewrapped += res.rows_affected() as usize;
+            }
+        }
+
+        Ok(rewrapped)
+    }
+
+    /// Runs [`Database::rewrap`] every `interval` until the server stops.
+    pub async fn rewrap_periodically(self, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            match self.rewrap().await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!(count, "rewrapped moodle tokens"),
+                Err(error) => tracing::error!(?error, "error rewrapping moodle tokens"),
+            }
+        }
+    }
+}
+
+/// The [`SecretStore`] of a single user. Their id token's subject is the
+/// transit context, so a ciphertext only decrypts for the user it belongs to.
+pub struct TransitStore {
+    database: Database,
+    subject: String,
+}
+
+#[async_trait]
+impl SecretStore for TransitStore {
+    #[tracing::instrument(skip(self, moodle_token))]
+    async fn put_moodle_token(
+        &self,
+        moodle_token: &MoodleToken,
+        registration: &Registration,
+    ) -> Result<(), SecretStoreError> {
+        let ciphertext = self
+            .database
+            .transit
+            .encrypt(moodle_token.expose_secret().as_bytes(), &self.subject)
+            .await?;
+        users::upsert(
+            &self.database.pool,
+            &self.subject,
+            ciphertext.as_bytes(),
+            registration,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_moodle_token(&self) -> Result<MoodleToken, SecretStoreError> {
+        let sealed = users::sealed_token(&self.database.pool, &self.subject)
+            .await?
+            .ok_or(SecretStoreError::NotFound)?;
+        let ciphertext = String::from_utf8(sealed).wrap_err("ciphertext is not utf-8")?;
+
+        let plaintext = self
+            .database
+            .transit
+            .decrypt(&ciphertext, &self.subject)
+            .await?;
+
+        Ok(std::str::from_utf8(plaintext.expose_secret())
+            .wrap_err("moodle token is not utf-8")?
+            .parse()
+            .wrap_err("malformed token inside database")?)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_registration(&self) -> Result<Registration, SecretStoreError> {
+        users::registration(&self.database.pool, &self.subject)
+            .await?
+            .ok_or(SecretStoreError::NotFound)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn record_validation(&self, validated_at: i64) -> Result<(), SecretStoreError> {
+        Ok(users::record_validation(&self.database.pool, &self.subject, validated_at).await?)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn mark_stale(&self) -> Result<(), SecretStoreError> {
+        Ok(users::mark_stale(&self.database.pool, &self.subject).await?)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn delete_moodle_token(&self) -> Result<(), SecretStoreError> {
+        Ok(users::delete(&self.database.pool, &self.subject).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::{ExposeSecret, Secret};
+    use serde_json::json;
+    use wiremock::{matchers, Mock, MockServer, ResponseTemplate};
+
+    use super::Database;
+    use crate::{
+        config::VaultConfig, moodle::token::MoodleToken, registration::Registration,
+        secret_store::SecretStore, vault::transit::Transit,
+    };
+
+    async fn database(mock: &MockServer) -> eyre::Result<Database> {
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await?;
+        sqlx::migrate!("../../db/migrations").run(&pool).await?;
+
+        let config = Box::leak(Box::new(VaultConfig {
+            url: mock.uri().parse()?,
+            suffix_path: "token".into(),
+        }));
+        let transit = Transit::new(
+            &reqwest::Client::new(),
+            config,
+            "mita",
+            Secret::new("mita-token".into()),
+        );
+
+        Ok(Database::new(pool, transit))
+    }
+
+    #[tokio::test]
+    async fn stores_only_ciphertext() -> eyre::Result<()> {
+        let mock = MockServer::start().await;
+        let database = database(&mock).await?;
+        let moodle_token = "ab".repeat(16).parse::<MoodleToken>()?;
+
+        Mock::given(matchers::path("/v1/transit/encrypt/mita"))
+            .and(matchers::header("X-Vault-Token", "mita-token"))
+            .and(matchers::body_partial_json(json!({
+                // base64 of "khang"
+                "context": "a2hhbmc=",
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "ciphertext": "vault:v1:sealed" },
+            })))
+            .expect(1)
+            .mount(&mock)
+            .await;
+        Mock::given(matchers::path("/v1/transit/decrypt/mita"))
+            .and(matchers::body_partial_json(json!({
+                "ciphertext": "vault:v1:sealed",
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                // base64 of "ab" * 16
+                "data": { "plaintext": "YWJhYmFiYWJhYmFiYWJhYmFiYWJhYmFiYWJhYmFiYWI=" },
+            })))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        let store = database.open("khang");
+        store
+            .put_moodle_token(&moodle_token, &Registration::default())
+            .await?;
+
+        let (stored,): (Vec<u8>,) = sqlx::query_as("SELECT token FROM users")
+            .fetch_one(&database.pool)
+            .await?;
+        assert_eq!(stored, b"vault:v1:sealed");
+
+        let token = store.get_moodle_token().await?;
+        assert_eq!(token.expose_secret(), moodle_token.expose_secret());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rewraps_outdated_ciphertexts() -> eyre::Result<()> {
+        let mock = MockServer::start().await;
+        let database = database(&mock).await?;
+
+        for (subject, token) in [("khang", "vault:v1:old"), ("minh", "vault:v2:new")] {
+            sqlx::query(
+                "INSERT INTO users (subject, token, moodle_username, registered_at, validated_at)
+                VALUES (?, ?, '', 0, 0)",
+            )
+            .bind(subject)
+            .bind(token.as_bytes())
+            .execute(&database.pool)
+            .await?;
+        }
+
+        Mock::given(matchers::path("/v1/transit/keys/mita"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "latest_version": 2 },
+            })))
+            .mount(&mock)
+            .await;
+        Mock::given(matchers::path("/v1/transit/rewrap/mita"))
+            .and(matchers::body_json(json!({
+                "batch_input": [{ "ciphertext": "vault:v1:old", "context": "a2hhbmc=" }],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "batch_results": [{ "ciphertext": "vault:v2:rewrapped" }] },
+            })))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        assert_eq!(database.rewrap().await?, 1);
+
+        let (token,): (Vec<u8>,) =
+            sqlx::query_as("SELECT token FROM users WHERE subject = 'khang'")
+                .fetch_one(&database.pool)
+                .await?;
+        assert_eq!(token, b"vault:v2:rewrapped");
+
+        Ok(())
+    }
+}
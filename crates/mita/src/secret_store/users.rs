@@ -0,0 +1,109 @@
+//! Rows of the `users` table, shared by the backends that keep moodle tokens
+//! in mita's own database. The token column holds whatever the backend sealed
+//! the token into.
+
+use sqlx::SqlitePool;
+
+use crate::registration::Registration;
+
+pub async fn upsert(
+    pool: &SqlitePool,
+    subject: &str,
+    sealed_token: &[u8],
+    registration: &Registration,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO users (subject, token, moodle_username, registered_at, validated_at, stale)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT (subject) DO UPDATE SET
+            token = excluded.token,
+            moodle_username = excluded.moodle_username,
+            registered_at = excluded.registered_at,
+            validated_at = excluded.validated_at,
+            stale = excluded.stale",
+    )
+    .bind(subject)
+    .bind(sealed_token)
+    .bind(&registration.moodle_username)
+    .bind(registration.registered_at)
+    .bind(registration.validated_at)
+    .bind(registration.stale)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn sealed_token(
+    pool: &SqlitePool,
+    subject: &str,
+) -> Result<Option<Vec<u8>>, sqlx::Error> {
+    let row: Option<(Vec<u8>,)> = sqlx::query_as("SELECT token FROM users WHERE subject = ?")
+        .bind(subject)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.map(|(token,)| token))
+}
+
+pub async fn registration(
+    pool: &SqlitePool,
+    subject: &str,
+) -> Result<Option<Registration>, sqlx::Error> {
+    let row: Option<(String, i64, i64, bool)> = sqlx::query_as(
+        "SELECT moodle_username, registered_at, validated_at, stale FROM users WHERE subject = ?",
+    )
+    .bind(subject)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(
+        |(moodle_username, registered_at, validated_at, stale)| Registration {
+            moodle_username,
+            registered_at,
+            validated_at,
+            stale,
+        },
+    ))
+}
+
+/// Records that moodle accepted the token at `validated_at`.
+pub async fn record_validation(
+    pool: &SqlitePool,
+    subject: &str,
+    validated_at: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET validated_at = ?, stale = 0 WHERE subject = ?")
+        .bind(validated_at)
+        .bind(subject)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn mark_stale(pool: &SqlitePool, subject: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET stale = 1 WHERE subject = ?")
+        .bind(subject)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn subjects(pool: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+    let rows: Vec<(String,)> = sqlx::query_as("SELECT subject FROM users")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.into_iter().map(|(subject,)| subject).collect())
+}
+
+pub async fn delete(pool: &SqlitePool, subject: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM users WHERE subject = ?")
+        .bind(subject)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use async_trait::async_trait;
+use sqlx::SqlitePool;
+
+use super::{SecretStore, SecretStoreError};
+use crate::{moodle::token::MoodleToken, registration::Registration, vault};
+
+/// Moodle tokens kept in vault's KV engine. How the token fares with moodle
+/// is kept in mita's own database, KV is only written when the token changes.
+pub struct VaultStore {
+    client: vault::Client,
+    pool: SqlitePool,
+    subject: String,
+}
+
+impl VaultStore {
+    pub fn new(client: vault::Client, pool: SqlitePool, subject: &str) -> Self {
+        Self {
+            client,
+            pool,
+            subject: subject.into(),
+        }
+    }
+
+    async fn upsert_validation(&self, validated_at: i64, stale: bool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO vault_validations (subject, validated_at, stale) VALUES (?, ?, ?)
+            ON CONFLICT (subject) DO UPDATE SET
+                validated_at = excluded.validated_at,
+                stale = excluded.stale",
+        )
+        .bind(&self.subject)
+        .bind(validated_at)
+        .bind(stale)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SecretStore for VaultStore {
+    async fn put_moodle_token(
+        &self,
+        moodle_token: &MoodleToken,
+        registration: &Registration,
+    ) -> Result<(), SecretStoreError> {
+        self.client
+            .put_moodle_token(moodle_token, registration)
+            .await?;
+        self.upsert_validation(registration.validated_at, registration.stale)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_moodle_token(&self) -> Result<MoodleToken, SecretStoreError> {
+        Ok(self.client.get_moodle_token().await?)
+    }
+
+    async fn get_registration(&self) -> Result<Registration, SecretStoreError> {
+        let mut registration = self.client.get_registration().await?;
+
+        let row: Option<(i64, bool)> =
+            sqlx::query_as("SELECT validated_at, stale FROM vault_validations WHERE subject = ?")
+                .bind(&self.subject)
+                .fetch_optional(&self.pool)
+                .await?;
+        // tokens registered before validations moved here only have the
+        // fields in vault
+        if let Some((validated_at, stale)) = row {
+            registration.validated_at = registration.validated_at.max(validated_at);
+            registration.stale = stale;
+        }
+
+        Ok(registration)
+    }
+
+    async fn record_validation(&self, validated_at: i64) -> Result<(), SecretStoreError> {
+        Ok(self.upsert_validation(validated_at, false).await?)
+    }
+
+    async fn mark_stale(&self) -> Result<(), SecretStoreError> {
+        sqlx::query(
+            "INSERT INTO vault_validations (subject, validated_at, stale) VALUES (?, 0, 1)
+            ON CONFLICT (subject) DO UPDATE SET stale = 1",
+        )
+        .bind(&self.subject)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_moodle_token(&self) -> Result<(), SecretStoreError> {
+        self.client.delete_moodle_token().await?;
+        sqlx::query("DELETE FROM vault_validations WHERE subject = ?")
+            .bind(&self.subject)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{matchers, Mock, MockServer, ResponseTemplate};
+
+    use super::VaultStore;
+    use crate::{
+        config::VaultConfig,
+        secret_store::SecretStore,
+        vault::{Client, TokenCache},
+    };
+
+    #[tokio::test]
+    async fn validations_stay_out_of_vault() -> eyre::Result<()> {
+        let mock = MockServer::start().await;
+        Mock::given(matchers::path("/v1/auth/jwt/login"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "auth": {
+                    "client_token": "client-token",
+                    "entity_id": "entity",
+                    "lease_duration": 3600,
+                    "renewable": true,
+                }
+            })))
+            .mount(&mock)
+            .await;
+        Mock::given(matchers::method("GET"))
+            .and(matchers::path("/v1/secret/data/entity/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": {"data": {
+                    "moodle_token": "ab".repeat(16),
+                    "moodle_username": "2012345",
+                    "registered_at": 1,
+                    "validated_at": 2,
+                }}
+            })))
+            .mount(&mock)
+            .await;
+
+        let config = Box::leak(Box::new(VaultConfig {
+            url: mock.uri().parse()?,
+            suffix_path: "token".into(),
+        }));
+        let client = Client::login(
+            &reqwest::Client::new(),
+            config,
+            &TokenCache::default(),
+            "khang",
+            "jwt",
+        )
+        .await?;
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await?;
+        sqlx::migrate!("../../db/migrations").run(&pool).await?;
+        let store = VaultStore::new(client, pool, "khang");
+
+        assert_eq!(store.get_registration().await?.validated_at, 2);
+
+        store.record_validation(5).await?;
+        let registration = store.get_registration().await?;
+        assert_eq!(registration.validated_at, 5);
+        assert!(!registration.stale);
+
+        store.mark_stale().await?;
+        let registration = store.get_registration().await?;
+        assert_eq!(registration.validated_at, 5);
+        assert!(registration.stale);
+
+        let writes = mock
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|req| {
+                req.url.path().starts_with("/v1/secret/")
+                    && req.method != wiremock::http::Method::Get
+            })
+            .count();
+        assert_eq!(writes, 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// Counts what each user is currently doing, to cap expensive work per user,
+/// such as building archives.
+#[derive(Clone, Default)]
+pub struct UserSlots(Arc<Mutex<HashMap<String, usize>>>);
+
+/// Frees its slot when dropped.
+pub struct Slot {
+    slots: UserSlots,
+    user: String,
+}
+
+impl UserSlots {
+    /// Takes one of `max` slots of `user`, or `None` when all are taken.
+    pub fn try_acquire(&self, user: &str, max: usize) -> Option<Slot> {
+        let mut slots = self.0.lock().expect("user slots poisoned");
+        let taken = slots.entry(user.into()).or_default();
+        if *taken >= max {
+            return None;
+        }
+        *taken += 1;
+
+        Some(Slot {
+            slots: self.clone(),
+            user: user.into(),
+        })
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let mut slots = self.slots.0.lock().expect("user slots poisoned");
+        if let Some(taken) = slots.get_mut(&self.user) {
+            *taken -= 1;
+            if *taken == 0 {
+                slots.remove(&self.user);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UserSlots;
+
+    #[test]
+    fn limits_each_user() {
+        let slots = UserSlots::default();
+
+        let first = slots.try_acquire("khang", 1);
+        assert!(first.is_some());
+        assert!(slots.try_acquire("khang", 1).is_none());
+        assert!(slots.try_acquire("minh", 1).is_some());
+
+        drop(first);
+        assert!(slots.try_acquire("khang", 1).is_some());
+    }
+}
@@ -1,3 +1,6 @@
+mod token_cache;
+pub mod transit;
+
 use async_trait::async_trait;
 use eyre::Context;
 use reqwest::StatusCode;
@@ -7,7 +10,10 @@ use thiserror::Error;
 use tracing::{info_span, Instrument};
 use url::Url;
 
-use crate::{config::VaultConfig, moodle::token::MoodleToken};
+use crate::{config::VaultConfig, moodle::token::MoodleToken, registration::Registration};
+
+use self::token_cache::Login;
+pub use self::token_cache::{CacheKey, TokenCache};
 
 #[derive(Clone)]
 pub struct Client {
@@ -15,6 +21,8 @@ pub struct Client {
     http_client: reqwest::Client,
     client_token: ClientToken,
     entity_id: EntityId,
+    cache: TokenCache,
+    cache_key: CacheKey,
 }
 
 #[derive(Clone, Deserialize)]
@@ -32,12 +40,48 @@ pub enum VaultError {
 }
 
 impl Client {
-    #[tracing::instrument(skip(http_client, config, id_token))]
+    /// Logs in with `id_token`, reusing the client token of a previous login
+    /// with the same id token while its lease lasts.
+    #[tracing::instrument(skip(http_client, config, cache, id_token))]
     pub async fn login(
         http_client: &reqwest::Client,
         config: &'static VaultConfig,
+        cache: &TokenCache,
+        subject: &str,
         id_token: &str,
     ) -> Result<Self, VaultError> {
+        let cache_key = CacheKey::new(subject, id_token);
+
+        let login = match cache.get(&cache_key) {
+            Some(login) if !login.expiring() => login,
+            Some(login) if login.renewable => {
+                match Self::renew(http_client, config, &login).await {
+                    Ok(login) => login,
+                    Err(error) => {
+                        tracing::warn!(?error, "error renewing vault token, logging in again");
+                        Self::jwt_login(http_client, config, id_token).await?
+                    }
+                }
+            }
+            _ => Self::jwt_login(http_client, config, id_token).await?,
+        };
+        cache.insert(cache_key.clone(), login.clone());
+
+        Ok(Self {
+            config,
+            http_client: http_client.clone(),
+            client_token: login.client_token,
+            entity_id: login.entity_id,
+            cache: cache.clone(),
+            cache_key,
+        })
+    }
+
+    async fn jwt_login(
+        http_client: &reqwest::Client,
+        config: &'static VaultConfig,
+        id_token: &str,
+    ) -> Result<Login, VaultError> {
         let res = http_client
             .post(config.url.join("v1/auth/jwt/login").unwrap())
             .json(&serde_json::json!({
@@ -60,26 +104,88 @@ impl Client {
         struct ResponseAuth {
             client_token: Secret<String>,
             entity_id: Secret<String>,
+            lease_duration: u64,
+            renewable: bool,
         }
 
         let res: Response = res.json().await.wrap_err("could not read body as json")?;
 
-        Ok(Self {
-            config,
-            http_client: http_client.clone(),
-            client_token: ClientToken(res.auth.client_token),
-            entity_id: EntityId(res.auth.entity_id),
-        })
+        Ok(Login::new(
+            ClientToken(res.auth.client_token),
+            EntityId(res.auth.entity_id),
+            res.auth.lease_duration,
+            res.auth.renewable,
+        ))
+    }
+
+    async fn renew(
+        http_client: &reqwest::Client,
+        config: &'static VaultConfig,
+        login: &Login,
+    ) -> Result<Login, VaultError> {
+        let res = http_client
+            .post(
+                config
+                    .url
+                    .join("v1/auth/token/renew-self")
+                    .wrap_err("cannot construct vault path")?,
+            )
+            .header("X-Vault-Token", login.client_token.0.expose_secret())
+            .json(&serde_json::json!({}))
+            .send()
+            .instrument(info_span!("renewing vault token"))
+            .await
+            .wrap_err("error sending request to vault")?
+            .try_into_vault_error()
+            .await?;
+
+        #[derive(Deserialize)]
+        struct Response {
+            auth: ResponseAuth,
+        }
+
+        #[derive(Deserialize)]
+        struct ResponseAuth {
+            client_token: Secret<String>,
+            lease_duration: u64,
+            renewable: bool,
+        }
+
+        let res: Response = res.json().await.wrap_err("could not read body as json")?;
+
+        Ok(Login::new(
+            ClientToken(res.auth.client_token),
+            login.entity_id.clone(),
+            res.auth.lease_duration,
+            res.auth.renewable,
+        ))
+    }
+
+    /// Forgets the cached client token once vault stops accepting it.
+    fn evict_on_forbidden<T>(&self, res: Result<T, VaultError>) -> Result<T, VaultError> {
+        if let Err(VaultError::Status(StatusCode::FORBIDDEN, _)) = &res {
+            self.cache.remove(&self.cache_key);
+        }
+        res
     }
 
     #[tracing::instrument(skip(self, moodle_token))]
-    pub async fn put_moodle_token(&self, moodle_token: &MoodleToken) -> Result<(), VaultError> {
-        self.http_client
+    pub async fn put_moodle_token(
+        &self,
+        moodle_token: &MoodleToken,
+        registration: &Registration,
+    ) -> Result<(), VaultError> {
+        let res = self
+            .http_client
             .post(self.data_path()?)
             .header("X-Vault-Token", self.client_token.0.expose_secret())
             .json(&serde_json::json!({
                 "data": {
                     "moodle_token": &moodle_token.expose_secret(),
+                    "moodle_username": &registration.moodle_username,
+                    "registered_at": registration.registered_at,
+                    "validated_at": registration.validated_at,
+                    "stale": registration.stale,
                 }
             }))
             .send()
@@ -87,16 +193,30 @@ impl Client {
             .await
             .wrap_err("error putting token in vault")?
             .try_into_vault_error()
-            .await?;
+            .await;
+        self.evict_on_forbidden(res)?;
 
         Ok(())
     }
 
+    /// Id of the vault entity the client logged in as, stable across logins.
+    pub fn entity_id(&self) -> &str {
+        self.entity_id.0.expose_secret()
+    }
+
     pub fn data_path(&self) -> Result<Url, VaultError> {
+        self.kv_path("data")
+    }
+
+    pub fn metadata_path(&self) -> Result<Url, VaultError> {
+        self.kv_path("metadata")
+    }
+
+    fn kv_path(&self, kind: &str) -> Result<Url, VaultError> {
         let mut url = self.config.url.clone();
         url.path_segments_mut()
             .map_err(|_| eyre::eyre!("vault url not a base"))?
-            .extend(&["v1", "secret", "data", self.entity_id.0.expose_secret(), ""]);
+            .extend(&["v1", "secret", kind, self.entity_id.0.expose_secret(), ""]);
         Ok(url
             .join(&self.config.suffix_path)
             .wrap_err("cannot construct vault path")?)
@@ -104,6 +224,21 @@ impl Client {
 
     #[tracing::instrument(skip(self))]
     pub async fn get_moodle_token(&self) -> Result<MoodleToken, VaultError> {
+        let secret = self.read_secret().await?;
+
+        Ok(secret
+            .moodle_token
+            .expose_secret()
+            .parse()
+            .wrap_err("malformed token inside vault")?)
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_registration(&self) -> Result<Registration, VaultError> {
+        Ok(self.read_secret().await?.registration)
+    }
+
+    async fn read_secret(&self) -> Result<MoodleSecret, VaultError> {
         let res = self
             .http_client
             .get(self.data_path()?)
@@ -113,7 +248,8 @@ impl Client {
             .await
             .wrap_err("error getting token from vault")?
             .try_into_vault_error()
-            .await?;
+            .await;
+        let res = self.evict_on_forbidden(res)?;
 
         #[derive(Deserialize)]
         struct Response {
@@ -122,28 +258,42 @@ impl Client {
 
         #[derive(Deserialize)]
         struct ResponseData {
-            data: ResponseDataData,
-        }
-
-        #[derive(Deserialize)]
-        struct ResponseDataData {
-            moodle_token: Secret<String>,
+            data: MoodleSecret,
         }
 
         let res: Response = res.json().await.wrap_err("could not read body as json")?;
 
-        Ok(res
-            .data
-            .data
-            .moodle_token
-            .expose_secret()
-            .parse()
-            .wrap_err("malformed token inside vault")?)
+        Ok(res.data.data)
     }
+
+    /// Permanently removes every version of the moodle token.
+    #[tracing::instrument(skip(self))]
+    pub async fn delete_moodle_token(&self) -> Result<(), VaultError> {
+        let res = self
+            .http_client
+            .delete(self.metadata_path()?)
+            .header("X-Vault-Token", self.client_token.0.expose_secret())
+            .send()
+            .instrument(info_span!("deleting moodle token from vault"))
+            .await
+            .wrap_err("error deleting token from vault")?
+            .try_into_vault_error()
+            .await;
+        self.evict_on_forbidden(res)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct MoodleSecret {
+    moodle_token: Secret<String>,
+    #[serde(flatten)]
+    registration: Registration,
 }
 
 #[async_trait]
-trait TryIntoVaultError: Sized {
+pub(crate) trait TryIntoVaultError: Sized {
     async fn try_into_vault_error(self) -> Result<Self, VaultError>;
 }
 
@@ -184,3 +334,111 @@ impl From<eyre::Error> for VaultError {
         Self::Unexpected(v)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{matchers, Mock, MockServer, ResponseTemplate};
+
+    use super::{Client, TokenCache, VaultError};
+    use crate::config::VaultConfig;
+
+    async fn vault(lease_duration: u64) -> (MockServer, &'static VaultConfig) {
+        let mock = MockServer::start().await;
+
+        Mock::given(matchers::path("/v1/auth/jwt/login"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "auth": {
+                    "client_token": "client-token",
+                    "entity_id": "entity",
+                    "lease_duration": lease_duration,
+                    "renewable": true,
+                }
+            })))
+            .named("jwt login")
+            .mount(&mock)
+            .await;
+
+        let config = Box::leak(Box::new(VaultConfig {
+            url: mock.uri().parse().unwrap(),
+            suffix_path: "token".into(),
+        }));
+
+        (mock, config)
+    }
+
+    async fn logins(mock: &MockServer) -> usize {
+        mock.received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|req| req.url.path() == "/v1/auth/jwt/login")
+            .count()
+    }
+
+    #[tokio::test]
+    async fn reuses_login_for_same_id_token() -> eyre::Result<()> {
+        let (mock, config) = vault(3600).await;
+        let http_client = reqwest::Client::new();
+        let cache = TokenCache::default();
+
+        Client::login(&http_client, config, &cache, "khang", "jwt").await?;
+        Client::login(&http_client, config, &cache, "khang", "jwt").await?;
+        assert_eq!(logins(&mock).await, 1);
+
+        Client::login(&http_client, config, &cache, "khang", "another jwt").await?;
+        assert_eq!(logins(&mock).await, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn renews_expiring_token() -> eyre::Result<()> {
+        let (mock, config) = vault(1).await;
+        let http_client = reqwest::Client::new();
+        let cache = TokenCache::default();
+
+        Mock::given(matchers::path("/v1/auth/token/renew-self"))
+            .and(matchers::header("X-Vault-Token", "client-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "auth": {
+                    "client_token": "client-token",
+                    "lease_duration": 3600,
+                    "renewable": true,
+                }
+            })))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        Client::login(&http_client, config, &cache, "khang", "jwt").await?;
+        Client::login(&http_client, config, &cache, "khang", "jwt").await?;
+        Client::login(&http_client, config, &cache, "khang", "jwt").await?;
+
+        assert_eq!(logins(&mock).await, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn evicts_forbidden_token() -> eyre::Result<()> {
+        let (mock, config) = vault(3600).await;
+        let http_client = reqwest::Client::new();
+        let cache = TokenCache::default();
+
+        Mock::given(matchers::path("/v1/secret/data/entity/token"))
+            .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+                "errors": ["permission denied"],
+            })))
+            .mount(&mock)
+            .await;
+
+        let vault = Client::login(&http_client, config, &cache, "khang", "jwt").await?;
+        let res = vault.get_moodle_token().await;
+        claims::assert_matches!(res, Err(VaultError::Status(status, _)) if status == 403);
+
+        Client::login(&http_client, config, &cache, "khang", "jwt").await?;
+        assert_eq!(logins(&mock).await, 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use sha2::{Digest, Sha256};
+
+use super::{ClientToken, EntityId};
+
+/// Tokens are renewed once two thirds of their lease has passed, or this long
+/// before they expire, whichever comes first.
+const RENEW_MARGIN: Duration = Duration::from_secs(60);
+
+/// Client tokens of recent vault logins, so that requests don't each create a
+/// new token in vault.
+#[derive(Clone, Default)]
+pub struct TokenCache(Arc<Mutex<HashMap<CacheKey, Login>>>);
+
+/// A login is only reused for the id token it was made with.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    subject: String,
+    id_token_hash: [u8; 32],
+}
+
+impl CacheKey {
+    pub fn new(subject: &str, id_token: &str) -> Self {
+        Self {
+            subject: subject.into(),
+            id_token_hash: Sha256::digest(id_token).into(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct Login {
+    pub client_token: ClientToken,
+    pub entity_id: EntityId,
+    pub renewable: bool,
+    lease: Duration,
+    /// `None` for tokens without a ttl.
+    expires_at: Option<Instant>,
+}
+
+impl Login {
+    pub fn new(
+        client_token: ClientToken,
+        entity_id: EntityId,
+        lease_duration: u64,
+        renewable: bool,
+    ) -> Self {
+        let lease = Duration::from_secs(lease_duration);
+        Self {
+            client_token,
+            entity_id,
+            renewable,
+            lease,
+            expires_at: (lease_duration != 0).then(|| Instant::now() + lease),
+        }
+    }
+
+    /// Whether the token should be renewed before being used.
+    pub fn expiring(&self) -> bool {
+        let margin = RENEW_MARGIN.max(self.lease / 3);
+        self.expires_at
+            .is_some_and(|at| at.saturating_duration_since(Instant::now()) <= margin)
+    }
+
+    fn expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Instant::now())
+    }
+}
+
+impl TokenCache {
+    pub(super) fn get(&self, key: &CacheKey) -> Option<Login> {
+        self.0
+            .lock()
+            .expect("vault token cache poisoned")
+            .get(key)
+            .filter(|login| !login.expired())
+            .cloned()
+    }
+
+    pub(super) fn insert(&self, key: CacheKey, login: Login) {
+        let mut logins = self.0.lock().expect("vault token cache poisoned");
+        logins.retain(|_, login| !login.expired());
+        logins.insert(key, login);
+    }
+
+    pub(super) fn remove(&self, key: &CacheKey) {
+        self.0
+            .lock()
+            .expect("vault token cache poisoned")
+            .remove(key);
+    }
+}
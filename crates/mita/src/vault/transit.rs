@@ -0,0 +1,221 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use eyre::Context;
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{info_span, Instrument};
+use url::Url;
+
+use super::{TryIntoVaultError, VaultError};
+use crate::config::VaultConfig;
+
+/// Vault's transit engine, used by mita with its own token rather than the
+/// users'.
+///
+/// The key must be created with `derived=true`, every ciphertext is bound to
+/// the context it was encrypted with.
+#[derive(Clone)]
+pub struct Transit {
+    config: &'static VaultConfig,
+    http_client: reqwest::Client,
+    key_name: String,
+    token: Secret<String>,
+}
+
+impl Transit {
+    pub fn new(
+        http_client: &reqwest::Client,
+        config: &'static VaultConfig,
+        key_name: &str,
+        token: Secret<String>,
+    ) -> Self {
+        Self {
+            config,
+            http_client: http_client.clone(),
+            key_name: key_name.into(),
+            token,
+        }
+    }
+
+    /// Returns a ciphertext of the form `vault:v{version}:...`.
+    #[tracing::instrument(skip(self, plaintext))]
+    pub async fn encrypt(&self, plaintext: &[u8], context: &str) -> Result<String, VaultError> {
+        #[derive(Deserialize)]
+        struct ResponseData {
+            ciphertext: String,
+        }
+
+        let data: ResponseData = self
+            .post(
+                "encrypt",
+                json!({
+                    "plaintext": BASE64.encode(plaintext),
+                    "context": BASE64.encode(context),
+                }),
+            )
+            .instrument(info_span!("encrypting with vault transit"))
+            .await?;
+
+        Ok(data.ciphertext)
+    }
+
+    #[tracing::instrument(skip(self, ciphertext))]
+    pub async fn decrypt(
+        &self,
+        ciphertext: &str,
+        context: &str,
+    ) -> Result<Secret<Vec<u8>>, VaultError> {
+        #[derive(Deserialize)]
+        struct ResponseData {
+            plaintext: Secret<String>,
+        }
+
+        let data: ResponseData = self
+            .post(
+                "decrypt",
+                json!({
+                    "ciphertext": ciphertext,
+                    "context": BASE64.encode(context),
+                }),
+            )
+            .instrument(info_span!("decrypting with vault transit"))
+            .await?;
+
+        Ok(Secret::new(
+            BASE64
+                .decode(data.plaintext.expose_secret())
+                .wrap_err("plaintext from vault is not base64")?,
+        ))
+    }
+
+    /// Re-encrypts ciphertexts under the latest version of the key without
+    /// revealing the plaintext. Items vault could not rewrap are `None`.
+    #[tracing::instrument(skip(self, items), fields(items = items.len()))]
+    pub async fn rewrap(
+        &self,
+        items: &[(String, String)],
+    ) -> Result<Vec<Option<String>>, VaultError> {
+        #[derive(Deserialize)]
+        struct ResponseData {
+            batch_results: Vec<BatchResult>,
+        }
+
+        #[derive(Deserialize)]
+        struct BatchResult {
+            ciphertext: Option<String>,
+            error: Option<String>,
+        }
+
+        let batch_input: Vec<_> = items
+            .iter()
+            .map(|(ciphertext, context)| {
+                json!({
+                    "ciphertext": ciphertext,
+                    "context": BASE64.encode(context),
+                })
+            })
+            .collect();
+
+        let data: ResponseData = self
+            .post("rewrap", json!({ "batch_input": batch_input }))
+            .instrument(info_span!("rewrapping with vault transit"))
+            .await?;
+
+        Ok(data
+            .batch_results
+            .into_iter()
+            .map(|result| {
+                if let Some(error) = result.error.filter(|e| !e.is_empty()) {
+                    tracing::warn!(%error, "vault could not rewrap ciphertext");
+                }
+                result.ciphertext
+            })
+            .collect())
+    }
+
+    /// Version of the key new ciphertexts are encrypted with.
+    #[tracing::instrument(skip(self))]
+    pub async fn latest_version(&self) -> Result<u32, VaultError> {
+        let res = self
+            .http_client
+            .get(self.path("keys")?)
+            .header("X-Vault-Token", self.token.expose_secret())
+            .send()
+            .instrument(info_span!("reading vault transit key"))
+            .await
+            .wrap_err("error sending request to vault")?
+            .try_into_vault_error()
+            .await?;
+
+        #[derive(Deserialize)]
+        struct Response {
+            data: ResponseData,
+        }
+
+        #[derive(Deserialize)]
+        struct ResponseData {
+            latest_version: u32,
+        }
+
+        let res: Response = res.json().await.wrap_err("could not read body as json")?;
+
+        Ok(res.data.latest_version)
+    }
+
+    async fn post<T: serde::de::DeserializeOwned>(
+        &self,
+        operation: &str,
+        body: serde_json::Value,
+    ) -> Result<T, VaultError> {
+        let res = self
+            .http_client
+            .post(self.path(operation)?)
+            .header("X-Vault-Token", self.token.expose_secret())
+            .json(&body)
+            .send()
+            .await
+            .wrap_err("error sending request to vault")?
+            .try_into_vault_error()
+            .await?;
+
+        #[derive(Deserialize)]
+        struct Response<T> {
+            data: T,
+        }
+
+        let res: Response<T> = res.json().await.wrap_err("could not read body as json")?;
+
+        Ok(res.data)
+    }
+
+    fn path(&self, operation: &str) -> Result<Url, VaultError> {
+        let mut url = self.config.url.clone();
+        url.path_segments_mut()
+            .map_err(|_| eyre::eyre!("vault url not a base"))?
+            .extend(&["v1", "transit", operation, &self.key_name]);
+        Ok(url)
+    }
+}
+
+/// Version of the key a transit ciphertext was encrypted with.
+pub fn key_version(ciphertext: &str) -> Option<u32> {
+    ciphertext
+        .strip_prefix("vault:v")?
+        .split_once(':')?
+        .0
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::key_version;
+
+    #[test]
+    fn parses_key_version() {
+        assert_eq!(key_version("vault:v1:abcd"), Some(1));
+        assert_eq!(key_version("vault:v12:abcd"), Some(12));
+        assert_eq!(key_version("vault:vx:abcd"), None);
+        assert_eq!(key_version("abcd"), None);
+    }
+}
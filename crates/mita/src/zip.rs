@@ -0,0 +1,242 @@
+//! Minimal streaming [ZIP](https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT)
+//! writer. Entries are stored without compression, most course material is
+//! compressed already, and sizes go in a data descriptor after each entry so
+//! nothing has to be buffered. No ZIP64, archives must stay under 4 GiB.
+
+use time::OffsetDateTime;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR: u32 = 0x0807_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+
+/// Sizes follow in a data descriptor, names are UTF-8.
+const FLAGS: u16 = 1 << 3 | 1 << 11;
+const VERSION: u16 = 20;
+
+#[derive(Default)]
+pub struct ZipWriter {
+    offset: u64,
+    entries: Vec<Entry>,
+    current: Option<Entry>,
+}
+
+struct Entry {
+    name: String,
+    time: u16,
+    date: u16,
+    offset: u32,
+    crc: Crc32,
+    size: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("zip archive too large")]
+pub struct TooLarge;
+
+impl ZipWriter {
+    /// Returns the local header of a new entry. Its contents follow through
+    /// [`ZipWriter::write`].
+    pub fn start_file(&mut self, name: &str, modified: i64) -> Result<Vec<u8>, TooLarge> {
+        assert!(self.current.is_none(), "previous entry not finished");
+
+        let (time, date) = dos_datetime(modified);
+        let entry = Entry {
+            name: name.into(),
+            time,
+            date,
+            offset: u32::try_from(self.offset).map_err(|_| TooLarge)?,
+            crc: Crc32::default(),
+            size: 0,
+        };
+
+        let mut out = Vec::with_capacity(30 + name.len());
+        put_u32(&mut out, LOCAL_HEADER);
+        put_u16(&mut out, VERSION);
+        put_u16(&mut out, FLAGS);
+        put_u16(&mut out, 0); // stored
+        put_u16(&mut out, time);
+        put_u16(&mut out, date);
+        put_u32(&mut out, 0); // crc, size and compressed size are in the descriptor
+        put_u32(&mut out, 0);
+        put_u32(&mut out, 0);
+        put_u16(&mut out, name.len() as u16);
+        put_u16(&mut out, 0);
+        out.extend_from_slice(name.as_bytes());
+
+        self.current = Some(entry);
+        self.offset += out.len() as u64;
+        Ok(out)
+    }
+
+    /// Accounts for a chunk of the current entry, which the caller sends on
+    /// unchanged.
+    pub fn write(&mut self, chunk: &[u8]) -> Result<(), TooLarge> {
+        let entry = self.current.as_mut().expect("no entry started");
+        entry.crc.update(chunk);
+        entry.size += chunk.len() as u64;
+        self.offset += chunk.len() as u64;
+        if entry.size > u32::MAX as u64 || self.offset > u32::MAX as u64 {
+            return Err(TooLarge);
+        }
+        Ok(())
+    }
+
+    /// Returns the data descriptor of the current entry.
+    pub fn finish_file(&mut self) -> Vec<u8> {
+        let entry = self.current.take().expect("no entry started");
+
+        let mut out = Vec::with_capacity(16);
+        put_u32(&mut out, DATA_DESCRIPTOR);
+        put_u32(&mut out, entry.crc.finish());
+        put_u32(&mut out, entry.size as u32);
+        put_u32(&mut out, entry.size as u32);
+
+        self.entries.push(entry);
+        self.offset += out.len() as u64;
+        out
+    }
+
+    /// Returns the central directory, the end of the archive.
+    pub fn finish(self) -> Result<Vec<u8>, TooLarge> {
+        assert!(self.current.is_none(), "last entry not finished");
+
+        let mut out = vec![];
+        for entry in &self.entries {
+            put_u32(&mut out, CENTRAL_HEADER);
+            put_u16(&mut out, VERSION);
+            put_u16(&mut out, VERSION);
+            put_u16(&mut out, FLAGS);
+            put_u16(&mut out, 0);
+            put_u16(&mut out, entry.time);
+            put_u16(&mut out, entry.date);
+            put_u32(&mut out, entry.crc.finish());
+            put_u32(&mut out, entry.size as u32);
+            put_u32(&mut out, entry.size as u32);
+            put_u16(&mut out, entry.name.len() as u16);
+            put_u16(&mut out, 0); // extra field
+            put_u16(&mut out, 0); // comment
+            put_u16(&mut out, 0); // disk
+            put_u16(&mut out, 0); // internal attributes
+            put_u32(&mut out, 0); // external attributes
+            put_u32(&mut out, entry.offset);
+            out.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let entries = u16::try_from(self.entries.len()).map_err(|_| TooLarge)?;
+        let offset = u32::try_from(self.offset).map_err(|_| TooLarge)?;
+        let size = out.len() as u32;
+        put_u32(&mut out, END_OF_CENTRAL_DIRECTORY);
+        put_u16(&mut out, 0);
+        put_u16(&mut out, 0);
+        put_u16(&mut out, entries);
+        put_u16(&mut out, entries);
+        put_u32(&mut out, size);
+        put_u32(&mut out, offset);
+        put_u16(&mut out, 0);
+
+        Ok(out)
+    }
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+/// MS-DOS time and date, which can't go before 1980.
+fn dos_datetime(unix: i64) -> (u16, u16) {
+    let t = OffsetDateTime::from_unix_timestamp(unix).unwrap_or(OffsetDateTime::UNIX_EPOCH);
+    if t.year() < 1980 {
+        return (0, 1 << 5 | 1);
+    }
+    let time = (t.hour() as u16) << 11 | (t.minute() as u16) << 5 | (t.second() as u16 / 2);
+    let date = ((t.year() - 1980).min(127) as u16) << 9 | (t.month() as u16) << 5 | t.day() as u16;
+    (time, date)
+}
+
+/// CRC-32 as used by ZIP, polynomial `0xEDB88320`.
+#[derive(Clone, Copy)]
+struct Crc32(u32);
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self(0xffff_ffff)
+    }
+}
+
+impl Crc32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 == 1 {
+                    crc >> 1 ^ 0xedb8_8320
+                } else {
+                    crc >> 1
+                };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = Self::TABLE[((self.0 ^ byte as u32) & 0xff) as usize] ^ self.0 >> 8;
+        }
+    }
+
+    fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Crc32, ZipWriter};
+
+    #[test]
+    fn crc32_check_value() {
+        let mut crc = Crc32::default();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finish(), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn writes_readable_directory() -> eyre::Result<()> {
+        let mut zip = ZipWriter::default();
+        let mut out = vec![];
+        for (name, content) in [("a/b.txt", &b"hello"[..]), ("c.txt", b"")] {
+            out.extend(zip.start_file(name, 1678000000)?);
+            zip.write(content)?;
+            out.extend_from_slice(content);
+            out.extend(zip.finish_file());
+        }
+        out.extend(zip.finish()?);
+
+        // end of central directory
+        let eocd = &out[out.len() - 22..];
+        assert_eq!(&eocd[..4], b"PK\x05\x06");
+        assert_eq!(u16::from_le_bytes([eocd[10], eocd[11]]), 2);
+        let cd_size = u32::from_le_bytes(eocd[12..16].try_into()?) as usize;
+        let cd_offset = u32::from_le_bytes(eocd[16..20].try_into()?) as usize;
+        assert_eq!(cd_offset + cd_size, out.len() - 22);
+        assert_eq!(&out[cd_offset..cd_offset + 4], b"PK\x01\x02");
+
+        // second local header right after the first entry and its descriptor
+        let second = 30 + "a/b.txt".len() + 5 + 16;
+        assert_eq!(&out[second..second + 4], b"PK\x03\x04");
+
+        Ok(())
+    }
+}
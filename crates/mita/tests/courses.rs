@@ -0,0 +1,71 @@
+use eyre::Context;
+use proptest::{
+    strategy::{Strategy, ValueTree},
+    test_runner::TestRunner,
+};
+use serde_json::{json, Value};
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+use crate::helper::test_app::TestApp;
+
+mod helper;
+
+#[tokio::test]
+async fn get_courses_successfully() -> eyre::Result<()> {
+    let mut app = TestApp::new().await?;
+
+    let mut runner = TestRunner::default();
+    let token = "[a-f0-9]{32}"
+        .new_tree(&mut runner)
+        .map_err(|e| eyre::eyre!(e))?
+        .current();
+
+    Mock::given(matchers::method("POST"))
+        .and(matchers::path("/webservice/rest/server.php"))
+        .and(matchers::body_string_contains(
+            "wsfunction=core_webservice_get_site_info",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "fullname": "khang",
+            "userid": 42,
+            "username": "2012345",
+        })))
+        .mount(&app.moodle_server)
+        .await;
+
+    Mock::given(matchers::method("POST"))
+        .and(matchers::path("/webservice/rest/server.php"))
+        .and(matchers::body_string_contains(
+            "wsfunction=core_enrol_get_users_courses",
+        ))
+        .and(matchers::body_string_contains("userid=42"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+            "id": 7,
+            "shortname": "CO3001",
+            "fullname": "Software Engineering",
+            "category": 3,
+            "progress": null,
+            "startdate": 1672531200,
+            "enddate": 0,
+            "hidden": false,
+            "isfavourite": true,
+        }])))
+        .expect(1)
+        .mount(&app.moodle_server)
+        .await;
+
+    app.id_token = helper::oauth2::get_code("khang", "").await.id_token;
+    app.put_token(token)
+        .await?
+        .error_for_status()
+        .wrap_err("got error status")?;
+
+    let body: Value = app.get_courses().await?.error_for_status()?.json().await?;
+
+    assert_eq!(body[0]["id"], 7);
+    assert_eq!(body[0]["shortname"], "CO3001");
+    assert_eq!(body[0]["end_date"], Value::Null);
+    assert_eq!(body[0]["favourite"], true);
+
+    Ok(())
+}
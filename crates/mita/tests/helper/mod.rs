@@ -1,2 +1,5 @@
+// each test binary only uses part of the helpers
+#![allow(dead_code)]
+
 pub mod oauth2;
 pub mod test_app;
@@ -20,7 +20,7 @@ pub async fn get_code(username: &str, claims: &str) -> TokenResult {
     let (redirect_uri, code) = oneshot_redirect_server();
     let client = reqwest::Client::new();
     client
-        .post(&format!("{}/authorize", OAUTH_ADDR))
+        .post(format!("{}/authorize", OAUTH_ADDR))
         .query(&[
             ("client_id", "client_id"),
             ("response_type", "code"),
@@ -37,7 +37,7 @@ pub async fn get_code(username: &str, claims: &str) -> TokenResult {
     let code = code.await;
 
     let res = client
-        .post(&format!("{}/token", OAUTH_ADDR))
+        .post(format!("{}/token", OAUTH_ADDR))
         .form(&[
             ("client_id", "client_id"),
             ("code", &code),
@@ -60,6 +60,38 @@ impl TestApp {
             .wrap_err_with(|| format!("error putting token {token}"))
     }
 
+    pub async fn put_credentials(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> eyre::Result<reqwest::Response> {
+        self.http_client
+            .put(format!("http://{}/token", self.addr))
+            .bearer_auth(&self.id_token)
+            .form(&[("username", username), ("password", password)])
+            .send()
+            .await
+            .wrap_err_with(|| format!("error putting credentials of {username}"))
+    }
+
+    pub async fn get_token(&self) -> eyre::Result<reqwest::Response> {
+        self.http_client
+            .get(format!("http://{}/token", self.addr))
+            .bearer_auth(&self.id_token)
+            .send()
+            .await
+            .wrap_err("error getting token status")
+    }
+
+    pub async fn delete_token(&self) -> eyre::Result<reqwest::Response> {
+        self.http_client
+            .delete(format!("http://{}/token", self.addr))
+            .bearer_auth(&self.id_token)
+            .send()
+            .await
+            .wrap_err("error deleting token")
+    }
+
     pub async fn get_info(&self) -> eyre::Result<reqwest::Response> {
         self.http_client
             .get(format!("http://{}/info", self.addr))
@@ -68,4 +100,13 @@ impl TestApp {
             .await
             .wrap_err("error getting user info")
     }
+
+    pub async fn get_courses(&self) -> eyre::Result<reqwest::Response> {
+        self.http_client
+            .get(format!("http://{}/courses", self.addr))
+            .bearer_auth(&self.id_token)
+            .send()
+            .await
+            .wrap_err("error getting courses")
+    }
 }
@@ -38,8 +38,10 @@ pub async fn put_token_and_get_info_successfully() -> eyre::Result<()> {
             "wsfunction=core_webservice_get_site_info",
         ))
         .and(matchers::body_string_contains("moodlewsrestformat=json"))
-        .respond_with(ResponseTemplate::new(200).set_body_json(&json!({
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
             "fullname": fullname,
+            "userid": 2,
+            "username": "2012345",
         })))
         // TODO: test if api only called once
         .mount(&app.moodle_server)
@@ -72,7 +74,7 @@ async fn shoud_400_when_no_token_provided() -> eyre::Result<()> {
 }
 
 #[tokio::test]
-async fn shoud_400_when_invalid_token_provided() -> eyre::Result<()> {
+async fn shoud_401_when_invalid_token_provided() -> eyre::Result<()> {
     let mut app = TestApp::new().await?;
     app.id_token = jsonwebtoken::encode(
         &Default::default(),
@@ -91,7 +93,7 @@ async fn shoud_400_when_invalid_token_provided() -> eyre::Result<()> {
 
     let res = app.get_info().await?;
 
-    assert_eq!(res.status(), 400);
+    assert_eq!(res.status(), 401);
 
     Ok(())
 }
@@ -144,7 +146,7 @@ pub async fn should_401_when_unknown_token() -> eyre::Result<()> {
         .current();
 
     Mock::given(any())
-        .respond_with(ResponseTemplate::new(200).set_body_json(&json!({
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
             "errorcode": "invalidtoken",
             "exception": "moodle_exception",
             "message": "Invalid token - token not found",
@@ -161,3 +163,106 @@ pub async fn should_401_when_unknown_token() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+pub async fn get_and_delete_registered_token() -> eyre::Result<()> {
+    let mut app = TestApp::new().await?;
+
+    let mut runner = TestRunner::default();
+    let token = "[a-f0-9]{32}"
+        .new_tree(&mut runner)
+        .map_err(|e| eyre::eyre!(e))?
+        .current();
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "fullname": "khang",
+            "userid": 2,
+            "username": "2012345",
+        })))
+        .mount(&app.moodle_server)
+        .await;
+
+    app.id_token = helper::oauth2::get_code("khang", "").await.id_token;
+    app.put_token(token.clone()).await?.error_for_status()?;
+
+    let res = app.get_token().await?.error_for_status()?;
+    let body: Value = res.json().await?;
+    assert_eq!(body["moodle_username"], "2012345");
+    assert!(!body.to_string().contains(&token));
+
+    let res = app.delete_token().await?;
+    assert_eq!(res.status(), 204);
+
+    let res = app.get_token().await?;
+    assert_eq!(res.status(), 404);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn put_credentials_successfully() -> eyre::Result<()> {
+    let mut app = TestApp::new().await?;
+
+    let mut runner = TestRunner::default();
+    let token = "[a-f0-9]{32}"
+        .new_tree(&mut runner)
+        .map_err(|e| eyre::eyre!(e))?
+        .current();
+
+    Mock::given(matchers::method("POST"))
+        .and(matchers::path("/login/token.php"))
+        .and(matchers::body_string_contains("username=2012345"))
+        .and(matchers::body_string_contains("service=moodle_mobile_app"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "token": token,
+            "privatetoken": null,
+        })))
+        .expect(1)
+        .mount(&app.moodle_server)
+        .await;
+
+    Mock::given(matchers::method("POST"))
+        .and(matchers::path("/webservice/rest/server.php"))
+        .and(matchers::body_string_contains(format!("wstoken={token}")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "fullname": "khang",
+            "userid": 2,
+            "username": "2012345",
+        })))
+        .expect(1)
+        .mount(&app.moodle_server)
+        .await;
+
+    app.id_token = helper::oauth2::get_code("khang", "").await.id_token;
+    app.put_credentials("2012345", "hunter2")
+        .await?
+        .error_for_status()
+        .wrap_err("got error status")?;
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn should_401_when_invalid_credentials() -> eyre::Result<()> {
+    let mut app = TestApp::new().await?;
+
+    Mock::given(matchers::path("/login/token.php"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "error": "Invalid login, please try again",
+            "errorcode": "invalidlogin",
+            "stacktrace": null,
+            "debuginfo": null,
+            "reproductionlink": null,
+        })))
+        .expect(1)
+        .mount(&app.moodle_server)
+        .await;
+
+    app.id_token = helper::oauth2::get_code("khang", "").await.id_token;
+    let res = app.put_credentials("2012345", "wrong").await?;
+
+    assert_eq!(res.status(), 401);
+
+    Ok(())
+}
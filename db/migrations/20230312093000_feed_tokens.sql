@@ -0,0 +1,7 @@
+CREATE TABLE feed_tokens (
+	id INTEGER PRIMARY KEY,
+	owner TEXT NOT NULL UNIQUE,
+	token_hash BLOB NOT NULL UNIQUE,
+	moodle_token BLOB NOT NULL,
+	created_at INTEGER NOT NULL
+)
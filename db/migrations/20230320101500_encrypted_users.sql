@@ -0,0 +1,11 @@
+-- the previous users table was never written to
+DROP TABLE users;
+
+CREATE TABLE users (
+	id INTEGER PRIMARY KEY,
+	subject TEXT NOT NULL UNIQUE,
+	token BLOB NOT NULL,
+	moodle_username TEXT NOT NULL,
+	registered_at INTEGER NOT NULL,
+	validated_at INTEGER NOT NULL
+)
@@ -0,0 +1,36 @@
+-- last seen grade of every grade item, per user
+CREATE TABLE grade_snapshots (
+	subject TEXT NOT NULL,
+	course_id INTEGER NOT NULL,
+	item_id INTEGER NOT NULL,
+	grade REAL,
+	grade_formatted TEXT,
+	PRIMARY KEY (subject, item_id)
+);
+
+CREATE TABLE grade_changes (
+	id INTEGER PRIMARY KEY,
+	subject TEXT NOT NULL,
+	course_id INTEGER NOT NULL,
+	course_name TEXT NOT NULL,
+	item_id INTEGER NOT NULL,
+	item_name TEXT NOT NULL,
+	kind TEXT NOT NULL,
+	old_grade REAL,
+	new_grade REAL,
+	old_grade_formatted TEXT,
+	new_grade_formatted TEXT,
+	detected_at INTEGER NOT NULL
+);
+
+CREATE INDEX grade_changes_subject_detected_at ON grade_changes (subject, detected_at);
+
+CREATE TABLE notification_channels (
+	id INTEGER PRIMARY KEY,
+	subject TEXT NOT NULL,
+	kind TEXT NOT NULL,
+	target TEXT NOT NULL,
+	created_at INTEGER NOT NULL
+);
+
+CREATE INDEX notification_channels_subject ON notification_channels (subject);
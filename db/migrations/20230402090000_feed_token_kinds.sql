@@ -0,0 +1,17 @@
+-- each user may have one feed token per kind of feed
+CREATE TABLE feed_tokens_new (
+	id INTEGER PRIMARY KEY,
+	owner TEXT NOT NULL,
+	kind TEXT NOT NULL,
+	token_hash BLOB NOT NULL UNIQUE,
+	moodle_token BLOB NOT NULL,
+	created_at INTEGER NOT NULL,
+	UNIQUE (owner, kind)
+);
+
+INSERT INTO feed_tokens_new (id, owner, kind, token_hash, moodle_token, created_at)
+SELECT id, owner, 'calendar', token_hash, moodle_token, created_at FROM feed_tokens;
+
+DROP TABLE feed_tokens;
+
+ALTER TABLE feed_tokens_new RENAME TO feed_tokens;
@@ -0,0 +1,6 @@
+-- vault entity of every user who registered a token with the vault backend,
+-- so that mita can read their token without their id token
+CREATE TABLE vault_entities (
+	subject TEXT PRIMARY KEY,
+	entity_id TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- set once moodle rejects the token, until the user registers a new one
+ALTER TABLE users ADD COLUMN stale INTEGER NOT NULL DEFAULT 0;
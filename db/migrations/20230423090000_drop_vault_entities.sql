@@ -0,0 +1,2 @@
+-- mita no longer reads vault backed tokens on its own
+DROP TABLE vault_entities;
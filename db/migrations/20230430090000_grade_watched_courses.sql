@@ -0,0 +1,10 @@
+-- courses snapshotted at least once, even with no grade items yet, so that
+-- only their first snapshot is a baseline
+CREATE TABLE grade_watched_courses (
+	subject TEXT NOT NULL,
+	course_id INTEGER NOT NULL,
+	PRIMARY KEY (subject, course_id)
+);
+
+INSERT INTO grade_watched_courses (subject, course_id)
+SELECT DISTINCT subject, course_id FROM grade_snapshots;
@@ -0,0 +1,6 @@
+-- key webhook payloads are signed with, handed to the user once when the
+-- channel is added; channels added before get one they never saw and have to
+-- be added again to verify signatures
+ALTER TABLE notification_channels ADD COLUMN secret TEXT NOT NULL DEFAULT '';
+
+UPDATE notification_channels SET secret = lower(hex(randomblob(32)));
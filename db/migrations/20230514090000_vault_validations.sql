@@ -0,0 +1,7 @@
+-- validations of tokens kept in vault, which would otherwise add a secret
+-- version each time
+CREATE TABLE vault_validations (
+	subject TEXT PRIMARY KEY NOT NULL,
+	validated_at INTEGER NOT NULL,
+	stale INTEGER NOT NULL DEFAULT 0
+)
@@ -18,6 +18,27 @@ cat << EOF | vault policy write kv-policy -
 path "secret/data/{{identity.entity.id}}/*" {
   capabilities = ["create", "read", "update", "patch", "delete", "list"]
 }
+path "secret/metadata/{{identity.entity.id}}/*" {
+  capabilities = ["read", "delete", "list"]
+}
+EOF
+
+vault secrets enable transit
+vault write -f transit/keys/mita derived=true
+
+cat << EOF | vault policy write mita-transit-policy -
+path "transit/encrypt/mita" {
+  capabilities = ["update"]
+}
+path "transit/decrypt/mita" {
+  capabilities = ["update"]
+}
+path "transit/rewrap/mita" {
+  capabilities = ["update"]
+}
+path "transit/keys/mita" {
+  capabilities = ["read"]
+}
 EOF
 
 vault auth enable jwt